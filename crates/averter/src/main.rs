@@ -1,11 +1,19 @@
 use actix_cors::Cors;
-use actix_web::{dev::Service, web, App, HttpServer};
-use futures_util::future::FutureExt;
+use actix_web::{
+	dev::{Service, ServiceResponse},
+	web, App, HttpResponse, HttpServer,
+};
+use futures_util::future::{ready, Either, FutureExt};
 use http::{header::HeaderName, HeaderValue};
 use sentry::{init, ClientOptions};
 use sentry_actix::Sentry;
 use std::{env::set_var, io::Result, str::FromStr, time::Instant};
-use utility::create_canister_client;
+use utility::{
+	brownout_response, check_rate_limit, compress_response, create_canister_client,
+	deprecation_headers, invalid_format_response, is_v1_route, load_configs, negotiate_encoding,
+	rate_limit_headers, rate_limited_response, record_usage, redirect_location,
+	watch_repository_dataset, watch_safety_overlay, watch_usage, with_context, RequestContext,
+};
 
 mod routes;
 mod utility;
@@ -33,9 +41,70 @@ async fn main() -> Result<()> {
 	// Enable backtraces for Sentry
 	set_var("RUST_BACKTRACE", "1");
 	create_canister_client();
+	load_configs();
+	actix_web::rt::spawn(watch_safety_overlay());
+	actix_web::rt::spawn(watch_repository_dataset());
+	actix_web::rt::spawn(watch_usage());
 
 	HttpServer::new(|| {
 		App::new()
+			.wrap_fn(|req, next| {
+				let limit = check_rate_limit(req.request());
+				let headers = limit.as_ref().map(rate_limit_headers).unwrap_or_default();
+				let rejection = match &limit {
+					Some(limit) if !limit.allowed => Some(rate_limited_response(limit)),
+					_ => invalid_format_response(req.request())
+						.or_else(|| brownout_response(req.request())),
+				};
+
+				match rejection {
+					Some(Ok(res)) => {
+						let mut res = req.into_response(res);
+						insert_headers(&mut res, headers);
+						Either::Left(ready(Ok(res.map_into_right_body())))
+					}
+					_ => Either::Right(next.call(req).map(move |res| {
+						res.map(|mut res| {
+							insert_headers(&mut res, headers);
+							res.map_into_left_body()
+						})
+					})),
+				}
+			})
+			.wrap_fn(|req, next| {
+				let context = RequestContext::from_request(req.request());
+				let headers = deprecation_headers(req.request());
+				with_context(context, || next.call(req)).map(move |res| {
+					res.map(|mut res| {
+						let req = res.request();
+						if is_v1_route(req.path()) {
+							let user_agent = req.headers().get("User-Agent");
+							record_usage(
+								req.match_pattern().as_deref().unwrap_or("unmatched"),
+								user_agent.and_then(|value| value.to_str().ok()),
+							);
+						}
+
+						insert_headers(&mut res, headers);
+
+						res
+					})
+				})
+			})
+			.wrap_fn(|req, next| match redirect_location(req.request()) {
+				Some(location) => {
+					let res = HttpResponse::PermanentRedirect()
+						.insert_header(("Location", location))
+						.finish();
+
+					Either::Left(ready(Ok(req.into_response(res).map_into_right_body())))
+				}
+
+				None => Either::Right(
+					next.call(req)
+						.map(|res| res.map(ServiceResponse::map_into_left_body)),
+				),
+			})
 			.wrap_fn(|req, next| {
 				let start = Instant::now();
 				next.call(req).map(move |res| {
@@ -60,18 +129,33 @@ async fn main() -> Result<()> {
 					})
 				})
 			})
+			.wrap_fn(|req, next| {
+				let encoding = negotiate_encoding(
+					req.headers()
+						.get("Accept-Encoding")
+						.and_then(|value| value.to_str().ok()),
+				);
+
+				next.call(req).map(move |res| {
+					res.map(|res| compress_response(res.map_into_boxed_body(), encoding))
+				})
+			})
 			.wrap(Sentry::new())
 			.wrap(Cors::default().allow_any_origin().send_wildcard())
 			.default_service(web::to(routes::utility::not_found))
-			.service(routes::utility::index)
-			.service(routes::utility::health)
-			.service(routes::package::lookup)
-			.service(routes::package::multi_lookup)
-			.service(routes::package::search)
-			.service(routes::repository::safety)
-			.service(routes::repository::search_ranking)
+			.configure(routes::configure)
 	})
 	.bind(("0.0.0.0", 3000))?
 	.run()
 	.await
 }
+
+/// Inserts headers with lowercase static names into a response
+fn insert_headers<B>(res: &mut ServiceResponse<B>, headers: Vec<(&'static str, String)>) {
+	for (name, value) in headers {
+		if let Ok(value) = HeaderValue::from_str(&value) {
+			res.headers_mut()
+				.insert(HeaderName::from_static(name), value);
+		}
+	}
+}
@@ -0,0 +1,53 @@
+use crate::utility::{
+	configure_mappings, record_terms, requested_architecture, Param, Request, Response,
+	RouteMapping, PACKAGE_FIELDS,
+};
+use actix_web::web::ServiceConfig;
+use serde_json::Value;
+use std::collections::HashMap;
+
+/// Declarative v1 routes that are generated from a mapping
+/// Routes that need custom logic have their own handlers under 'routes/'
+pub static MAPPINGS: &[RouteMapping] = &[RouteMapping {
+	path: "/community/packages/lookup",
+	endpoint: "/jailbreak/package/multi",
+	params: &[
+		Param::Query("packages", "ids"),
+		Param::Local("architecture"),
+	],
+	required: &["packages"],
+	fields: PACKAGE_FIELDS,
+	group: Some("package"),
+	hook: Some(prepare_packages),
+}];
+
+/// Registers every mapped route
+pub fn configure(config: &mut ServiceConfig) {
+	configure_mappings(config, MAPPINGS);
+}
+
+/// Records package terms for suggestions and filters packages by architecture
+fn prepare_packages(
+	req: &Request,
+	params: &HashMap<&'static str, String>,
+	items: Vec<Value>,
+) -> Result<Vec<Value>, Response> {
+	let architecture = params.get("architecture").map(String::as_str);
+	let architecture = requested_architecture(req, architecture)?;
+	record_terms(items.iter().flat_map(|item| {
+		["package", "name"]
+			.into_iter()
+			.filter_map(|key| item.get(key).and_then(Value::as_str))
+	}));
+
+	Ok(items
+		.into_iter()
+		.filter(|item| match architecture {
+			Some(architecture) => match item.get("architecture").and_then(Value::as_str) {
+				Some(value) => architecture.matches(value),
+				None => false,
+			},
+			None => true,
+		})
+		.collect())
+}
@@ -1,3 +1,47 @@
+use actix_web::web::{get, ServiceConfig};
+
+pub mod mappings;
 pub mod package;
 pub mod repository;
 pub mod utility;
+
+/// Declares every hand-written route once, generating both its registration and its path
+macro_rules! routes {
+	($($path:literal => $handler:path),* $(,)?) => {
+		/// Paths of every hand-written route
+		const ROUTES: &[&str] = &[$($path),*];
+
+		fn configure_routes(config: &mut ServiceConfig) {
+			$(config.route($path, get().to($handler));)*
+		}
+	};
+}
+
+routes! {
+	"/" => utility::index,
+	"/healthz" => utility::health,
+	"/metrics" => utility::metrics,
+	"/usage" => utility::usage,
+	"/community/packages" => package::lookup,
+	"/community/packages/search" => package::search,
+	"/community/repositories" => repository::lookup,
+	"/community/repositories/packages" => repository::packages,
+	"/community/repositories/safety" => repository::safety,
+	"/community/repositories/search" => repository::search_ranking,
+}
+
+/// Registers every route, including mapped routes and the v2 passthrough
+pub fn configure(config: &mut ServiceConfig) {
+	configure_routes(config);
+	mappings::configure(config);
+	utility::configure_passthrough(config);
+}
+
+/// Paths of every route that averter handles, used for the closest route hint
+/// The v2 passthrough is left out since it never falls through to a 404
+pub fn handled_routes() -> impl Iterator<Item = &'static str> {
+	ROUTES
+		.iter()
+		.copied()
+		.chain(mappings::MAPPINGS.iter().map(|mapping| mapping.path))
+}
@@ -1,46 +1,17 @@
-use crate::utility::{api_respond, error_respond, fetch_v2, Request, Response};
-use actix_web::{get, web::Query};
+use crate::utility::{
+	api_respond, error_respond, fetch_v2, package_json, project, record_packages,
+	requested_architecture, requested_projection, Envelope, PackageRecord, Request, Response,
+	PACKAGE_FIELDS,
+};
+use actix_web::web::Query;
 use serde::{Deserialize, Serialize};
-use serde_json::{json, Value};
+use serde_json::Value;
 
 #[derive(Serialize, Deserialize)]
 struct Params {
 	id: String,
-}
-
-#[derive(Serialize, Deserialize)]
-struct Repository {
-	slug: String,
-	suite: String,
-	uri: String,
-	tier: u8,
-	aliases: Vec<String>,
-	name: Option<String>,
-	version: Option<String>,
-	component: Option<String>,
-}
-
-#[derive(Serialize, Deserialize)]
-struct Data {
-	package: String,
-	architecture: String,
-	price: String,
-	#[serde(rename = "isCurrent")]
-	is_current: bool,
-	version: String,
-	name: Option<String>,
-	description: Option<String>,
-	author: Option<String>,
-	maintainer: Option<String>,
-	depiction: Option<String>,
-	#[serde(rename = "sileoDepiction")]
-	sileo_depiction: Option<String>,
-	header: Option<String>,
-	#[serde(rename = "tintColor")]
-	tint_color: Option<String>,
-	icon: Option<String>,
-	section: Option<String>,
-	repository: Repository,
+	repository: Option<String>,
+	architecture: Option<String>,
 }
 
 #[derive(Serialize, Deserialize)]
@@ -49,66 +20,69 @@ struct CanisterQuery {}
 #[derive(Serialize, Deserialize)]
 struct CanisterResponse {
 	date: String,
-	data: Vec<Data>,
+	data: Vec<PackageRecord>,
 }
 
-#[get("/community/packages")]
 pub async fn lookup(req: Request) -> Response {
-	let id = match Query::<Params>::from_query(req.query_string()) {
-		Ok(query) => query.id.clone(),
+	let (id, repository, architecture) = match Query::<Params>::from_query(req.query_string()) {
+		Ok(query) => (
+			query.id.clone(),
+			query.repository.clone(),
+			query.architecture.clone(),
+		),
 		Err(_) => return error_respond(400, "Missing query parameter: \'id\'"),
 	};
 
+	let architecture = match requested_architecture(&req, architecture.as_deref()) {
+		Ok(architecture) => architecture,
+		Err(err) => return err,
+	};
+
+	let projection = match requested_projection(&req, PACKAGE_FIELDS, &[]) {
+		Ok(projection) => projection,
+		Err(err) => return err,
+	};
+
 	let query = CanisterQuery {}; // No query parameters
 	let uri = &format!("/jailbreak/package/{id}").to_owned();
 
-	let (mut response, is_cached) =
-		match fetch_v2::<CanisterQuery, CanisterResponse>(query, uri).await {
-			Ok(response) => response,
-			Err(err) => return err,
-		};
+	let (response, is_cached) = match fetch_v2::<CanisterQuery, CanisterResponse>(query, uri).await
+	{
+		Ok(response) => response,
+		Err(err) => return err,
+	};
+
+	if !is_cached {
+		record_packages(&response.data);
+	}
 
-	let mut data = response.data.iter_mut();
-	let data: Option<Value> = data.find_map(|item| match item.is_current {
-		true => Some(json!({
-			"identifier": item.package,
-			"architecture": item.architecture,
-			"price": item.price,
-			"latestVersion": item.version,
-			"name": item.name,
-			"description": item.description,
-			"author": item.author,
-			"maintainer": item.maintainer,
-			"depiction": item.depiction,
-			"nativeDepiction": item.sileo_depiction,
-			"header": item.header,
-			"tintColor": item.tint_color,
-			"packageIcon": item.icon,
-			"section": item.section,
-			"repository": {
-				"slug": item.repository.slug,
-				"aliases": item.repository.aliases,
-				"uri": item.repository.uri,
-				"version": item.repository.version,
-				"suite": item.repository.suite,
-				"component": item.repository.component,
-				"ranking": item.repository.tier,
-				"name": item.repository.name,
-			}
-		})),
-		false => None,
-	});
+	// Packages can exist in multiple repositories, so we pick one deterministically
+	// Pinned repositories win, otherwise the highest tier (lowest number) is used
+	let data = response.data.iter();
+	let data: Option<Value> = data
+		.filter(|item| item.is_current)
+		.filter(|item| match architecture {
+			Some(architecture) => architecture.matches(&item.architecture),
+			None => true,
+		})
+		.filter(|item| match (&repository, &item.repository) {
+			(Some(repository), Some(source)) => source.matches(repository).is_some(),
+			(Some(_), None) => false,
+			(None, _) => true,
+		})
+		.min_by_key(|item| match &item.repository {
+			Some(repository) => (repository.tier, repository.slug.clone()),
+			None => (u8::MAX, String::new()),
+		})
+		.map(|item| project(projection.as_ref(), package_json(item)));
 
 	match &data {
 		Some(_) => (),
-		None => return error_respond(404, "Package not found"),
+		None => match repository {
+			Some(_) => return error_respond(404, "Package not found in repository"),
+			None => return error_respond(404, "Package not found"),
+		},
 	};
 
-	api_respond(
-		200,
-		is_cached,
-		json!({
-			"data": data,
-		}),
-	)
+	api_respond(200, is_cached, Envelope::new(data))
 }
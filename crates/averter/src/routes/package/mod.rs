@@ -1,7 +1,5 @@
 mod lookup;
-mod multi_lookup;
 mod search;
 
 pub use self::lookup::*;
-pub use self::multi_lookup::*;
 pub use self::search::*;
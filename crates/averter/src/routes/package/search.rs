@@ -1,44 +1,22 @@
-use crate::utility::{api_respond, error_respond, fetch_v2, Request, Response};
-use actix_web::{get, web::Query};
+use crate::utility::{
+	api_respond, bool_param, error_respond, fetch_v2, normalize_query, package_json, parse_price,
+	project, record_packages, requested_architecture, requested_projection, suggest, Envelope,
+	PackageRecord, QueryRoute, Request, Response, PACKAGE_FIELDS,
+};
+use actix_web::web::Query;
 use serde::{Deserialize, Serialize};
 use serde_json::{json, Value};
+use std::{collections::HashMap, mem::replace};
+
+const SUGGESTION_LIMIT: usize = 5;
 
 #[derive(Serialize, Deserialize)]
 struct Params {
 	query: String,
-}
-
-#[derive(Serialize, Deserialize)]
-struct Repository {
-	slug: String,
-	suite: String,
-	uri: String,
-	tier: u8,
-	aliases: Vec<String>,
-	name: Option<String>,
-	version: Option<String>,
-	component: Option<String>,
-}
-
-#[derive(Serialize, Deserialize)]
-struct Data {
-	package: String,
-	architecture: String,
-	price: String,
-	version: String,
-	name: Option<String>,
-	description: Option<String>,
-	author: Option<String>,
-	maintainer: Option<String>,
-	depiction: Option<String>,
-	#[serde(rename = "sileoDepiction")]
-	sileo_depiction: Option<String>,
-	header: Option<String>,
-	#[serde(rename = "tintColor")]
-	tint_color: Option<String>,
-	icon: Option<String>,
-	section: Option<String>,
-	repository: Repository,
+	architecture: Option<String>,
+	paid: Option<String>,
+	rerank: Option<String>,
+	dedupe: Option<String>,
 }
 
 #[derive(Serialize, Deserialize)]
@@ -49,63 +27,153 @@ struct CanisterQuery {
 #[derive(Serialize, Deserialize)]
 struct CanisterResponse {
 	date: String,
-	data: Vec<Data>,
+	data: Vec<PackageRecord>,
 }
 
-#[get("/community/packages/search")]
 pub async fn search(req: Request) -> Response {
-	let query = match Query::<Params>::from_query(req.query_string()) {
-		Ok(query) => query.query.clone(),
+	let params = match Query::<Params>::from_query(req.query_string()) {
+		Ok(query) => query.into_inner(),
 		Err(_) => return error_respond(400, "Missing query parameter: \'query\'"),
 	};
 
-	let query = CanisterQuery { q: query };
-	let (mut response, is_cached) =
-		match fetch_v2::<CanisterQuery, CanisterResponse>(query, "/jailbreak/package/search").await
-		{
-			Ok(response) => response,
-			Err(err) => return err,
-		};
+	let (paid, rerank, dedupe) = match (
+		bool_param(params.paid.as_deref(), "paid"),
+		bool_param(params.rerank.as_deref(), "rerank"),
+		bool_param(params.dedupe.as_deref(), "dedupe"),
+	) {
+		(Ok(paid), Ok(rerank), Ok(dedupe)) => (paid, rerank, dedupe),
+		(Err(err), _, _) | (_, Err(err), _) | (_, _, Err(err)) => return err,
+	};
+
+	let architecture = match requested_architecture(&req, params.architecture.as_deref()) {
+		Ok(architecture) => architecture,
+		Err(err) => return err,
+	};
+
+	let projection = match requested_projection(&req, PACKAGE_FIELDS, &["alsoAvailableIn"]) {
+		Ok(projection) => projection,
+		Err(err) => return err,
+	};
+
+	let query = normalize_query(QueryRoute::PackageSearch, &params.query);
+	let canister_query = CanisterQuery { q: query.clone() };
+
+	let (response, is_cached) = match fetch_v2::<CanisterQuery, CanisterResponse>(
+		canister_query,
+		"/jailbreak/package/search",
+	)
+	.await
+	{
+		Ok(response) => response,
+		Err(err) => return err,
+	};
+
+	if !is_cached {
+		record_packages(&response.data);
+	}
+
+	let data = response.data.iter();
+	let mut data = data
+		.filter(|item| match architecture {
+			Some(architecture) => architecture.matches(&item.architecture),
+			None => true,
+		})
+		.filter(|item| match paid {
+			Some(paid) => parse_price(&item.price).free != paid,
+			None => true,
+		})
+		.collect::<Vec<&PackageRecord>>();
+
+	// Sorting is stable, so Canister's order is kept between equally relevant results
+	if rerank.unwrap_or(false) {
+		data.sort_by_key(|item| (relevance(&query, item), tier(item)));
+	}
+
+	let data = match dedupe.unwrap_or(false) {
+		true => collapse_duplicates(data),
+		false => data.into_iter().map(package_json).collect::<Vec<Value>>(),
+	};
 
-	let data = response.data.iter_mut();
 	let data = data
-		.map(|item| {
-			let package = json!({
-				"identifier": item.package,
-				"architecture": item.architecture,
-				"price": item.price,
-				"latestVersion": item.version,
-				"name": item.name,
-				"description": item.description,
-				"author": item.author,
-				"maintainer": item.maintainer,
-				"depiction": item.depiction,
-				"nativeDepiction": item.sileo_depiction,
-				"header": item.header,
-				"tintColor": item.tint_color,
-				"packageIcon": item.icon,
-				"section": item.section,
-				"repository": {
-					"slug": item.repository.slug,
-					"aliases": item.repository.aliases,
-					"uri": item.repository.uri,
-					"version": item.repository.version,
-					"suite": item.repository.suite,
-					"component": item.repository.component,
-					"ranking": item.repository.tier,
-					"name": item.repository.name,
+		.into_iter()
+		.map(|item| project(projection.as_ref(), item))
+		.collect::<Vec<Value>>();
+
+	// Suggestions come from the local vocabulary to avoid another round trip
+	if data.is_empty() {
+		return api_respond(
+			200,
+			is_cached,
+			Envelope::new(data).field("suggestions", suggest(&query, SUGGESTION_LIMIT)),
+		);
+	}
+
+	api_respond(200, is_cached, Envelope::new(data))
+}
+
+/// Ranks how well a package matches the query, lower is better
+/// Exact identifier matches come first, followed by identifier and name prefixes
+fn relevance(query: &str, item: &PackageRecord) -> u8 {
+	let query = query.trim().to_lowercase();
+	let identifier = item.package.to_lowercase();
+	let name = item.name.as_deref().unwrap_or_default().to_lowercase();
+
+	if identifier == query {
+		0
+	} else if name == query {
+		1
+	} else if identifier.starts_with(&query) || name.starts_with(&query) {
+		2
+	} else {
+		3
+	}
+}
+
+/// Returns the tier of the repository hosting a package, lower is better
+fn tier(item: &PackageRecord) -> u8 {
+	match &item.repository {
+		Some(repository) => repository.tier,
+		None => u8::MAX,
+	}
+}
+
+/// Collapses packages with the same identifier and version into a single result
+/// The copy from the highest tier is kept and the others are listed in 'alsoAvailableIn'
+fn collapse_duplicates(data: Vec<&PackageRecord>) -> Vec<Value> {
+	let mut indices: HashMap<(&str, &str), usize> = HashMap::new();
+	let mut collapsed: Vec<(&PackageRecord, Vec<String>)> = Vec::new();
+
+	for item in data {
+		let key = (item.package.as_str(), item.version.as_str());
+		match indices.get(&key) {
+			Some(index) => {
+				// Keep the copy from the highest tier repository as the primary result
+				let (kept, others) = &mut collapsed[*index];
+				let duplicate = match tier(item) < tier(kept) {
+					true => replace(kept, item),
+					false => item,
+				};
+
+				if let Some(repository) = &duplicate.repository {
+					others.push(repository.slug.clone());
 				}
-			});
+			}
+			None => {
+				indices.insert(key, collapsed.len());
+				collapsed.push((item, Vec::new()));
+			}
+		}
+	}
+
+	collapsed
+		.into_iter()
+		.map(|(item, also_available_in)| {
+			let mut package = package_json(item);
+			if let Value::Object(fields) = &mut package {
+				fields.insert("alsoAvailableIn".to_owned(), json!(also_available_in));
+			}
 
 			package
 		})
-		.collect::<Vec<Value>>();
-
-	api_respond(
-		200,
-		is_cached,
-		json!({
-			"data": data,
-		}),
-	)
+		.collect()
 }
@@ -0,0 +1,36 @@
+use crate::utility::{
+	api_respond, error_respond, project, repository_dataset, repository_json, requested_projection,
+	Envelope, Request, Response, REPOSITORY_FIELDS,
+};
+use actix_web::web::Query;
+use serde::{Deserialize, Serialize};
+
+#[derive(Serialize, Deserialize)]
+struct Params {
+	id: String,
+}
+
+pub async fn lookup(req: Request) -> Response {
+	let id = match Query::<Params>::from_query(req.query_string()) {
+		Ok(query) => query.id.clone(),
+		Err(_) => return error_respond(400, "Missing query parameter: \'id\'"),
+	};
+
+	let projection = match requested_projection(&req, REPOSITORY_FIELDS, &[]) {
+		Ok(projection) => projection,
+		Err(err) => return err,
+	};
+
+	let dataset = match repository_dataset().await {
+		Ok(dataset) => dataset,
+		Err(err) => return err,
+	};
+
+	let item = match dataset.resolve(&id) {
+		Some(item) => item,
+		None => return error_respond(404, "Repository not found"),
+	};
+
+	let data = project(projection.as_ref(), repository_json(item));
+	api_respond(200, true, Envelope::new(data))
+}
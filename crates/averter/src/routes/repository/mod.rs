@@ -1,5 +1,9 @@
+mod lookup;
+mod packages;
 mod safety;
 mod search_ranking;
 
+pub use self::lookup::*;
+pub use self::packages::*;
 pub use self::safety::*;
 pub use self::search_ranking::*;
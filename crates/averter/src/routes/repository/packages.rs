@@ -0,0 +1,109 @@
+use crate::utility::{
+	api_respond, error_respond, fetch_v2, package_json, project, record_packages,
+	repository_dataset, requested_architecture, requested_projection, Envelope, PackageRecord,
+	Pagination, Request, Response, PACKAGE_FIELDS,
+};
+use actix_web::web::Query;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+const DEFAULT_LIMIT: u32 = 100;
+const MAX_LIMIT: u32 = 250;
+
+#[derive(Serialize, Deserialize)]
+struct Params {
+	id: String,
+	page: Option<u32>,
+	limit: Option<u32>,
+	section: Option<String>,
+	architecture: Option<String>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct CanisterQuery {
+	page: u32,
+	limit: u32,
+}
+
+#[derive(Serialize, Deserialize)]
+struct CanisterResponse {
+	date: String,
+	data: Vec<PackageRecord>,
+}
+
+pub async fn packages(req: Request) -> Response {
+	let params = match Query::<Params>::from_query(req.query_string()) {
+		Ok(query) => query.into_inner(),
+		Err(_) => return error_respond(400, "Missing query parameter: \'id\'"),
+	};
+
+	let architecture = match requested_architecture(&req, params.architecture.as_deref()) {
+		Ok(architecture) => architecture,
+		Err(err) => return err,
+	};
+
+	let projection = match requested_projection(&req, PACKAGE_FIELDS, &[]) {
+		Ok(projection) => projection,
+		Err(err) => return err,
+	};
+
+	let page = params.page.unwrap_or(1).max(1);
+	let limit = params.limit.unwrap_or(DEFAULT_LIMIT).clamp(1, MAX_LIMIT);
+
+	// Aliases and URIs are resolved to the slug Canister expects
+	let dataset = match repository_dataset().await {
+		Ok(dataset) => dataset,
+		Err(err) => return err,
+	};
+
+	let repository = match dataset.resolve(&params.id) {
+		Some(repository) => repository,
+		None => return error_respond(404, "Repository not found"),
+	};
+
+	let query = CanisterQuery { page, limit };
+	let uri = format!("/jailbreak/repository/{}/packages", repository.slug);
+
+	let (response, is_cached) = match fetch_v2::<CanisterQuery, CanisterResponse>(query, &uri).await
+	{
+		Ok(response) => response,
+		Err(err) => return err,
+	};
+
+	if !is_cached {
+		record_packages(&response.data);
+	}
+
+	let count = response.data.len();
+	let data = response
+		.data
+		.into_iter()
+		.filter(|item| match architecture {
+			Some(architecture) => architecture.matches(&item.architecture),
+			None => true,
+		})
+		.filter(|item| match (&params.section, &item.section) {
+			(Some(section), Some(item)) => section.eq_ignore_ascii_case(item),
+			(Some(_), None) => false,
+			(None, _) => true,
+		})
+		.map(|mut item| {
+			// The repository isn't always embedded when listing by repository
+			if item.repository.is_none() {
+				item.repository = Some(repository.clone());
+			}
+
+			project(projection.as_ref(), package_json(&item))
+		})
+		.collect::<Vec<Value>>();
+
+	api_respond(
+		200,
+		is_cached,
+		Envelope::new(data).pagination(Pagination {
+			page,
+			limit,
+			has_next: count >= limit as usize,
+		}),
+	)
+}
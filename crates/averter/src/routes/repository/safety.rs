@@ -1,7 +1,11 @@
-use crate::utility::{api_respond, error_respond, fetch_v2, Request, Response};
-use actix_web::{get, web::Query};
+use crate::utility::{
+	api_respond, check_overlay, error_respond, fetch_v2, increment_metric, normalize_uri, Envelope,
+	Request, Response,
+};
+use actix_web::web::Query;
 use serde::{Deserialize, Serialize};
 use serde_json::{json, Value};
+use std::collections::HashMap;
 
 #[derive(Serialize, Deserialize)]
 struct Params {
@@ -14,6 +18,13 @@ struct Data {
 	safe: bool,
 }
 
+/// A safety verdict along with where it came from
+struct Verdict {
+	safe: bool,
+	reason: Option<String>,
+	source: &'static str,
+}
+
 #[derive(Serialize, Deserialize)]
 struct CanisterQuery {
 	uris: String,
@@ -26,7 +37,6 @@ struct CanisterResponse {
 	data: Vec<Data>,
 }
 
-#[get("/community/repositories/safety")]
 pub async fn safety(req: Request) -> Response {
 	let (uris, is_single) = match Query::<Params>::from_query(req.query_string()) {
 		Ok(query) => match query.queries.clone() {
@@ -42,44 +52,117 @@ pub async fn safety(req: Request) -> Response {
 		Err(_) => return error_respond(400, "Missing query paramter \'query\' or \'queries\'"),
 	};
 
-	let query = CanisterQuery { uris };
-	let (mut response, is_cached) =
-		match fetch_v2::<CanisterQuery, CanisterResponse>(query, "/jailbreak/repository/safety")
-			.await
+	// Canister may normalise URIs differently, so results are matched by normalised key
+	let uris = uris
+		.split(',')
+		.map(|uri| uri.trim().to_owned())
+		.filter(|uri| !uri.is_empty())
+		.collect::<Vec<String>>();
+
+	if uris.is_empty() {
+		return error_respond(400, "Missing query parameter: \'query\' or \'queries\'");
+	}
+
+	let mut keys = uris
+		.iter()
+		.map(|uri| normalize_uri(uri))
+		.collect::<Vec<String>>();
+
+	keys.sort();
+	keys.dedup();
+
+	// The operator overlay is consulted first so new verdicts apply without waiting on Canister
+	let mut verdicts: HashMap<String, Verdict> = HashMap::new();
+	for key in &keys {
+		if let Some(verdict) = check_overlay(key).await {
+			increment_metric(match verdict.safe {
+				true => "safety_overlay_allow",
+				false => "safety_overlay_deny",
+			});
+
+			verdicts.insert(
+				key.clone(),
+				Verdict {
+					safe: verdict.safe,
+					reason: verdict.reason,
+					source: "overlay",
+				},
+			);
+		}
+	}
+
+	let keys = keys
+		.into_iter()
+		.filter(|key| !verdicts.contains_key(key))
+		.collect::<Vec<String>>();
+
+	let mut is_cached = false;
+	if !keys.is_empty() {
+		let query = CanisterQuery {
+			uris: keys
+				.iter()
+				.map(|key| format!("https://{key}"))
+				.collect::<Vec<String>>()
+				.join(","),
+		};
+
+		let (response, cached) = match fetch_v2::<CanisterQuery, CanisterResponse>(
+			query,
+			"/jailbreak/repository/safety",
+		)
+		.await
 		{
 			Ok(response) => response,
 			Err(err) => return err,
 		};
 
-	match is_single && response.count == 1 {
-		true => api_respond(
-			200,
-			is_cached,
-			json!({
-				"data": match response.data[0].safe {
-					true => "safe",
-					false => "unsafe",
-				}
-			}),
-		),
+		is_cached = cached;
+		for item in response.data {
+			verdicts.entry(normalize_uri(&item.uri)).or_insert(Verdict {
+				safe: item.safe,
+				reason: None,
+				source: "canister",
+			});
+		}
+	}
+
+	let status = |uri: &str| match verdicts.get(&normalize_uri(uri)) {
+		Some(verdict) => match verdict.safe {
+			true => ("safe", verdict.reason.clone(), Some(verdict.source)),
+			false => ("unsafe", verdict.reason.clone(), Some(verdict.source)),
+		},
+		None => ("unknown", None, None),
+	};
+
+	match is_single && uris.len() == 1 {
+		true => {
+			let (status, reason, source) = status(&uris[0]);
+			api_respond(
+				200,
+				is_cached,
+				Envelope::new(status)
+					.field("reason", reason)
+					.field("source", source),
+			)
+		}
 
 		false => {
-			let data = response.data.iter_mut();
-			let data = data
-				.map(|item| {
+			let data = uris
+				.iter()
+				.map(|uri| {
+					let (status, reason, source) = status(uri);
 					let safety = json!({
-						"repositoryURI": item.uri,
-						"status": match item.safe {
-							true => "safe",
-							false => "unsafe",
-						}
+						"repositoryURI": uri,
+						"status": status,
+						"reason": reason,
+						"source": source,
 					});
 
 					safety
 				})
 				.collect::<Vec<Value>>();
 
-			api_respond(200, is_cached, json!({ "data": data }))
+			api_respond(200, is_cached, Envelope::new(data))
 		}
 	}
 }
@@ -1,26 +1,23 @@
-use std::collections::HashSet;
-
-use crate::utility::{api_respond, error_respond, fetch_v2, Request, Response};
-use actix_web::{get, web::Query};
+use std::{cmp::Ordering, collections::BTreeSet, time::Duration};
+
+use crate::utility::{
+	api_respond, error_respond, fetch_v2, normalize_query, project, repository_dataset,
+	repository_json, requested_projection, Envelope, Projection, QueryRoute, RepositoryRecord,
+	Request, Response, REPOSITORY_FIELDS,
+};
+use actix_web::web::Query;
 use serde::{Deserialize, Serialize};
-use serde_json::{json, Value};
+use serde_json::{json, Map, Value};
+use tokio::time::timeout;
+
+const SEARCH_TIMEOUT: Duration = Duration::from_secs(5);
+const FALLBACK_LIMIT: usize = 25;
 
 #[derive(Serialize, Deserialize)]
 struct Params {
 	query: Option<String>,
 	ranking: Option<String>,
-}
-
-#[derive(Serialize, Deserialize)]
-struct Data {
-	slug: String,
-	suite: String,
-	uri: String,
-	tier: u8,
-	aliases: Vec<String>,
-	name: Option<String>,
-	version: Option<String>,
-	component: Option<String>,
+	sort: Option<String>,
 }
 
 #[derive(Serialize, Deserialize)]
@@ -32,16 +29,22 @@ struct CanisterQuery {
 #[derive(Serialize, Deserialize)]
 struct CanisterResponse {
 	date: String,
-	data: Vec<Data>,
+	data: Vec<RepositoryRecord>,
 }
 
-#[get("/community/repositories/search")]
 pub async fn search_ranking(req: Request) -> Response {
+	let projection = match requested_projection(&req, REPOSITORY_FIELDS, &[]) {
+		Ok(projection) => projection,
+		Err(err) => return err,
+	};
+
 	match Query::<Params>::from_query(req.query_string()) {
 		Ok(query) => match query.query.clone() {
-			Some(query) => return repository_search(query).await,
+			Some(query) => return repository_search(query, projection).await,
 			None => match query.ranking.clone() {
-				Some(ranking) => return repository_ranking(ranking).await,
+				Some(ranking) => {
+					return repository_ranking(ranking, query.sort.clone(), projection).await
+				}
 				None => error_respond(400, "Missing query parameter \'query\' or \'ranking\'"),
 			},
 		},
@@ -50,93 +53,140 @@ pub async fn search_ranking(req: Request) -> Response {
 	}
 }
 
-async fn repository_search(query: String) -> Response {
-	let query = CanisterQuery {
-		q: Some(query),
+async fn repository_search(query: String, projection: Option<Projection>) -> Response {
+	let query = normalize_query(QueryRoute::RepositorySearch, &query);
+	let canister_query = CanisterQuery {
+		q: Some(query.clone()),
 		rank: None,
 	};
 
-	let (mut response, is_cached) =
-		match fetch_v2::<CanisterQuery, CanisterResponse>(query, "/jailbreak/repository/search")
-			.await
-		{
-			Ok(response) => response,
-			Err(err) => return err,
-		};
+	let response = timeout(
+		SEARCH_TIMEOUT,
+		fetch_v2::<CanisterQuery, CanisterResponse>(canister_query, "/jailbreak/repository/search"),
+	)
+	.await;
 
-	let data = response.data.iter_mut();
+	let (response, is_cached) = match response {
+		Ok(Ok(response)) => response,
+		Ok(Err(err)) => match is_upstream_failure(&err) {
+			true => return repository_search_fallback(&query, err, projection).await,
+			false => return err,
+		},
+		Err(_) => {
+			let err = error_respond(504, "Timed out waiting for Canister");
+			return repository_search_fallback(&query, err, projection).await;
+		}
+	};
+
+	let data = response.data.iter();
 	let data = data
-		.map(|item| {
-			let package = json!({
-				"slug": item.slug,
-				"aliases": item.aliases,
-				"uri": item.uri,
-				"version": item.version,
-				"suite": item.suite,
-				"component": item.component,
-				"ranking": item.tier,
-				"name": item.name,
-			});
-
-			package
-		})
+		.map(|item| project(projection.as_ref(), repository_json(item)))
 		.collect::<Vec<Value>>();
 
-	api_respond(
-		200,
-		is_cached,
-		json!({
-			"data": data,
-		}),
-	)
+	api_respond(200, is_cached, Envelope::new(data))
+}
+
+/// Searches the local repository dataset when Canister search is unavailable
+/// Responses are flagged as degraded and never publicly cached
+async fn repository_search_fallback(
+	query: &str,
+	err: Response,
+	projection: Option<Projection>,
+) -> Response {
+	let dataset = match repository_dataset().await {
+		Ok(dataset) => dataset,
+		Err(_) => return err,
+	};
+
+	let data = dataset
+		.fuzzy_search(query, FALLBACK_LIMIT)
+		.into_iter()
+		.map(|item| project(projection.as_ref(), repository_json(item)))
+		.collect::<Vec<Value>>();
+
+	api_respond(200, false, Envelope::new(data).field("degraded", true))
 }
 
-async fn repository_ranking(ranking: String) -> Response {
-	let ranks = ranking
-		.split(',')
-		.filter(|rank| matches!(rank, &"1" | &"2" | &"3" | &"4" | &"5"))
-		.collect::<HashSet<&str>>();
+/// Checks if an error response was caused by Canister being unavailable
+/// Client errors (like an invalid query) are passed through as-is
+fn is_upstream_failure(err: &Response) -> bool {
+	match err {
+		Ok(response) => response.status().is_server_error(),
+		Err(_) => true,
+	}
+}
 
-	let query = CanisterQuery {
-		q: None,
-		rank: Some("*".to_owned()),
+async fn repository_ranking(
+	ranking: String,
+	sort: Option<String>,
+	projection: Option<Projection>,
+) -> Response {
+	let ranking = normalize_query(QueryRoute::RepositoryRanking, &ranking);
+	let mut ranks = BTreeSet::new();
+	for rank in ranking.split(',').map(|rank| rank.trim()) {
+		match rank {
+			"*" => ranks.extend(1..=5),
+			_ => match rank.parse::<u8>() {
+				Ok(rank @ 1..=5) => {
+					ranks.insert(rank);
+				}
+				_ => return error_respond(400, &format!("Invalid ranking: \'{rank}\'")),
+			},
+		}
+	}
+
+	let sort = match sort.as_deref() {
+		Some("tier") | None => Sort::Tier,
+		Some("name") => Sort::Name,
+		Some("slug") => Sort::Slug,
+		Some(_) => {
+			return error_respond(400, "Invalid sort, expected \'tier\', \'name\' or \'slug\'")
+		}
+	};
+
+	let dataset = match repository_dataset().await {
+		Ok(dataset) => dataset,
+		Err(err) => return err,
 	};
 
-	let (mut response, is_cached) =
-		match fetch_v2::<CanisterQuery, CanisterResponse>(query, "/jailbreak/repository/ranking")
-			.await
-		{
-			Ok(response) => response,
-			Err(err) => return err,
+	let mut counts = Map::new();
+	let mut repositories = Vec::new();
+	for rank in &ranks {
+		let tier = dataset.tier(*rank).collect::<Vec<&RepositoryRecord>>();
+		counts.insert(rank.to_string(), json!(tier.len()));
+		repositories.extend(tier);
+	}
+
+	// Ties are always broken by slug so the ordering is stable between refreshes
+	repositories.sort_by(|left, right| {
+		let ordering = match sort {
+			Sort::Tier => left.tier.cmp(&right.tier),
+			Sort::Name => sort_name(left).cmp(&sort_name(right)),
+			Sort::Slug => Ordering::Equal,
 		};
 
-	let data = response.data.iter_mut();
-	let data = data
-		.filter_map(|item| {
-			if ranks.contains(item.tier.to_string().as_str()) {
-				let package = json!({
-					"slug": item.slug,
-					"aliases": item.aliases,
-					"uri": item.uri,
-					"version": item.version,
-					"suite": item.suite,
-					"component": item.component,
-					"ranking": item.tier,
-					"name": item.name,
-				});
-
-				Some(package)
-			} else {
-				None
-			}
-		})
+		ordering.then_with(|| left.slug.cmp(&right.slug))
+	});
+
+	let data = repositories
+		.into_iter()
+		.map(|item| project(projection.as_ref(), repository_json(item)))
 		.collect::<Vec<Value>>();
 
-	api_respond(
-		200,
-		is_cached,
-		json!({
-			"data": data,
-		}),
-	)
+	api_respond(200, true, Envelope::new(data).field("counts", counts))
+}
+
+enum Sort {
+	Tier,
+	Name,
+	Slug,
+}
+
+/// Returns the case-insensitive sort key for a repository name
+/// Repositories without a name are sorted by their slug
+fn sort_name(repository: &RepositoryRecord) -> String {
+	match &repository.name {
+		Some(name) => name.to_lowercase(),
+		None => repository.slug.to_lowercase(),
+	}
 }
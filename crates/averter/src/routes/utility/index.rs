@@ -1,9 +1,7 @@
 use crate::utility::{http_respond, Request, Response};
-use actix_web::get;
 use chrono::{Datelike, Utc};
 use serde_json::json;
 
-#[get("/")]
 pub async fn index(req: Request) -> Response {
 	let name = format!(
 		"{} ({})",
@@ -0,0 +1,11 @@
+use crate::utility::{http_respond, metrics_snapshot, Request, Response};
+use serde_json::json;
+
+pub async fn metrics(_req: Request) -> Response {
+	http_respond(
+		200,
+		json!({
+			"counters": metrics_snapshot()
+		}),
+	)
+}
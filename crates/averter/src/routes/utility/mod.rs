@@ -1,7 +1,13 @@
 mod health;
 mod index;
+mod metrics;
 mod not_found;
+mod passthrough;
+mod usage;
 
 pub use self::health::*;
 pub use self::index::*;
+pub use self::metrics::*;
 pub use self::not_found::*;
+pub use self::passthrough::*;
+pub use self::usage::*;
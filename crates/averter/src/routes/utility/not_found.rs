@@ -1,6 +1,58 @@
-use crate::utility::{api_respond, Request, Response};
-use serde_json::json;
+use crate::routes::handled_routes;
+use crate::utility::{api_respond, edit_distance, Envelope, Request, Response};
 
-pub async fn not_found(_req: Request) -> Response {
-	api_respond(404, false, json!({}))
+/// Known v1 routes that averter does not implement, with their v2 replacements
+/// Routes without a replacement were removed entirely in v2
+const RETIRED_ROUTES: &[(&str, Option<&str>)] = &[
+	(
+		"/community/repositories/ranking",
+		Some("/jailbreak/repository/ranking"),
+	),
+	(
+		"/community/packages/download",
+		Some("/jailbreak/download/ingest"),
+	),
+];
+
+pub async fn not_found(req: Request) -> Response {
+	let path = req.path().trim_end_matches('/');
+	if let Some((_, replacement)) = RETIRED_ROUTES.iter().find(|(route, _)| *route == path) {
+		// The docs mirror the API paths, so the replacement has a page of its own
+		let docs = match replacement {
+			Some(endpoint) => v2_link(env!("CANISTER_DOCS_ENDPOINT"), endpoint),
+			None => env!("CANISTER_DOCS_ENDPOINT").to_owned(),
+		};
+
+		let replacement =
+			replacement.map(|endpoint| v2_link(env!("CANISTER_API_ENDPOINT"), endpoint));
+
+		return api_respond(
+			410,
+			false,
+			Envelope::error("This route has been retired, please migrate to the v2 API")
+				.field("replacement", replacement)
+				.field("docs", docs),
+		);
+	}
+
+	api_respond(
+		404,
+		false,
+		Envelope::empty().field("hint", closest_route(path)),
+	)
+}
+
+/// Finds the known route closest to the requested path
+/// Only returns a route if it is within a few edits of the path
+fn closest_route(path: &str) -> Option<&'static str> {
+	handled_routes()
+		.map(|route| (edit_distance(path, route), route))
+		.filter(|(distance, route)| *distance <= (route.len() / 4).max(2))
+		.min_by_key(|(distance, _)| *distance)
+		.map(|(_, route)| route)
+}
+
+/// Builds the link to a v2 endpoint under a base URL
+fn v2_link(base: &str, endpoint: &str) -> String {
+	format!("{}/v2{endpoint}", base.trim_end_matches('/'))
 }
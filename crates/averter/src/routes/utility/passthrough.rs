@@ -0,0 +1,201 @@
+use crate::utility::{
+	canister, handle_error, increment_metric, CachedBody, Request, Response, LRU,
+};
+use actix_web::{
+	web::{self, Bytes, ServiceConfig},
+	HttpResponse, HttpResponseBuilder,
+};
+use futures_util::{io::AsyncReadExt, stream::unfold};
+use http::StatusCode;
+use lazy_static::lazy_static;
+use std::{env::var, str::FromStr};
+use surf::http::Method;
+use tokio::sync::Mutex;
+
+const CHUNK_SIZE: usize = 8192;
+const PASSTHROUGH_CACHE_SIZE: usize = 50;
+
+/// Largest upstream body kept in the passthrough cache, larger bodies are only streamed
+const MAX_CACHED_BODY_SIZE: usize = 1024 * 1024;
+
+/// Headers that only apply to a single connection and are never forwarded
+const HOP_HEADERS: &[&str] = &[
+	"connection",
+	"content-length",
+	"host",
+	"keep-alive",
+	"transfer-encoding",
+	"upgrade",
+	"accept-encoding",
+];
+
+/// Request headers that carry credentials, so the response may be specific to the client
+const CREDENTIAL_HEADERS: &[&str] = &[
+	"authorization",
+	"proxy-authorization",
+	"cookie",
+	"x-api-key",
+];
+
+/// An upstream response stored with its status and headers so cache hits replay it as-is
+#[derive(Clone)]
+struct CachedResponse {
+	status: StatusCode,
+	headers: Vec<(String, String)>,
+	body: Bytes,
+}
+
+lazy_static! {
+	/// Whether unmatched '/v2/...' requests are proxied to Canister, from 'AVERTER_V2_PASSTHROUGH'
+	static ref PASSTHROUGH_ENABLED: bool = matches!(var("AVERTER_V2_PASSTHROUGH").as_deref(), Ok("true"));
+
+	static ref PASSTHROUGH_CACHE: Mutex<LRU<CachedResponse>> =
+		Mutex::new(LRU::with_capacity(PASSTHROUGH_CACHE_SIZE));
+}
+
+/// Registers the v2 passthrough proxy if it is enabled
+pub fn configure_passthrough(config: &mut ServiceConfig) {
+	if *PASSTHROUGH_ENABLED {
+		config.route("/v2/{tail:.*}", web::route().to(passthrough));
+	}
+}
+
+/// Proxies a v2 request straight to Canister, preserving headers, status and body
+/// Successful GET responses are streamed to the client and stored in the LRU cache
+/// Requests with credentials are never cached since their responses may be private
+async fn passthrough(req: Request, body: Bytes) -> Response {
+	let path = match req.query_string().is_empty() {
+		true => req.path().to_owned(),
+		false => format!("{}?{}", req.path(), req.query_string()),
+	};
+
+	let is_cacheable = req.method() == http::Method::GET
+		&& !CREDENTIAL_HEADERS
+			.iter()
+			.any(|name| req.headers().contains_key(*name));
+
+	increment_metric("v2_passthrough");
+
+	if is_cacheable {
+		if let Some(cached) = PASSTHROUGH_CACHE.lock().await.get(path.clone()) {
+			increment_metric("v2_passthrough_cached");
+			let mut builder = HttpResponseBuilder::new(cached.status);
+			for (name, value) in &cached.headers {
+				builder.append_header((name.as_str(), value.as_str()));
+			}
+
+			let mut response = builder.body(cached.body);
+			response.extensions_mut().insert(CachedBody);
+			return Ok(response);
+		}
+	}
+
+	let method = match Method::from_str(req.method().as_str()) {
+		Ok(method) => method,
+		Err(_) => return Ok(HttpResponse::MethodNotAllowed().finish()),
+	};
+
+	let mut request = canister().request(method, &path);
+	for (name, value) in req.headers() {
+		if HOP_HEADERS.contains(&name.as_str()) {
+			continue;
+		}
+
+		if let Ok(value) = value.to_str() {
+			request = request.header(name.as_str(), value);
+		}
+	}
+
+	if !body.is_empty() {
+		request = request.body(body.to_vec());
+	}
+
+	let mut response = match request.await {
+		Ok(response) => response,
+		Err(err) => {
+			handle_error(&err.into_inner());
+			return Ok(HttpResponse::BadGateway().finish());
+		}
+	};
+
+	let status = match StatusCode::from_u16(response.status().into()) {
+		Ok(status) => status,
+		Err(_) => StatusCode::BAD_GATEWAY,
+	};
+
+	let mut headers = Vec::new();
+	let mut builder = HttpResponseBuilder::new(status);
+	for (name, values) in response.iter() {
+		if HOP_HEADERS.contains(&name.as_str()) {
+			continue;
+		}
+
+		for value in values {
+			builder.append_header((name.as_str(), value.as_str()));
+			headers.push((name.as_str().to_owned(), value.as_str().to_owned()));
+		}
+	}
+
+	// Only successful GET responses are cached, mirroring 'fetch_v2'
+	// Responses that set cookies or opt out of shared caching are specific to the client
+	let is_private = headers.iter().any(|(name, value)| {
+		name.eq_ignore_ascii_case("set-cookie")
+			|| (name.eq_ignore_ascii_case("cache-control")
+				&& (value.contains("private") || value.contains("no-store")))
+	});
+
+	let cached = match is_cacheable && status == StatusCode::OK && !is_private {
+		true => Some((
+			path,
+			CachedResponse {
+				status,
+				headers,
+				body: Bytes::new(),
+			},
+		)),
+		false => None,
+	};
+
+	let body = response.take_body();
+	let stream = unfold(
+		(Some(body), Vec::new(), cached),
+		|(body, mut buffer, mut cached)| async move {
+			let mut body = body?;
+			let mut chunk = vec![0; CHUNK_SIZE];
+
+			match body.read(&mut chunk).await {
+				Ok(0) => {
+					if let Some((path, mut cached)) = cached {
+						cached.body = Bytes::from(buffer);
+						PASSTHROUGH_CACHE.lock().await.insert(path, cached);
+					}
+
+					None
+				}
+
+				Ok(read) => {
+					chunk.truncate(read);
+					if cached.is_some() {
+						match buffer.len() + chunk.len() > MAX_CACHED_BODY_SIZE {
+							true => {
+								cached = None;
+								buffer = Vec::new();
+							}
+							false => buffer.extend_from_slice(&chunk),
+						}
+					}
+
+					let chunk: Result<Bytes, actix_web::Error> = Ok(Bytes::from(chunk));
+					Some((chunk, (Some(body), buffer, cached)))
+				}
+
+				Err(err) => {
+					let chunk: Result<Bytes, actix_web::Error> = Err(err.into());
+					Some((chunk, (None, buffer, None)))
+				}
+			}
+		},
+	);
+
+	Ok(builder.streaming(stream))
+}
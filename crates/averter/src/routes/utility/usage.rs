@@ -0,0 +1,6 @@
+use crate::utility::{http_respond, usage_report, Request, Response};
+
+/// Reports v1 usage by day, route and client for retirement planning
+pub async fn usage(_req: Request) -> Response {
+	http_respond(200, usage_report())
+}
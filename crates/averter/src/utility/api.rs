@@ -1,7 +1,7 @@
 use super::{canister, error_respond, handle_error, Response, LRU};
 use lazy_static::lazy_static;
 use serde::{de::DeserializeOwned, Deserialize, Serialize};
-use serde_json::{from_str, to_string, to_value, Value};
+use serde_json::{from_str, to_string};
 use std::sync::Arc;
 use surf::StatusCode;
 use tokio::sync::Mutex;
@@ -13,43 +13,6 @@ struct HTTPError {
 	error: Option<String>,
 }
 
-/// Merges two JSON objects together in the order of left, right
-/// If the object is a strictly-typed struct, it is serialized into a Value
-pub fn merge_json<L: Serialize, R: Serialize>(left: L, right: R) -> Value {
-	let mut left = match to_value(left) {
-		Ok(value) => value,
-		Err(err) => {
-			handle_error(&err.into());
-			return Value::Null;
-		}
-	};
-
-	let right = match to_value(right) {
-		Ok(value) => value,
-		Err(err) => {
-			handle_error(&err.into());
-			return Value::Null;
-		}
-	};
-
-	merge_json_value(&mut left, right);
-	left
-}
-
-// Modified to be a bit more readable and concise
-// Original: https://stackoverflow.com/questions/47070876/how-can-i-merge-two-json-objects-with-rust
-fn merge_json_value(left: &mut Value, right: Value) {
-	match (left, right) {
-		(&mut Value::Object(ref mut left), Value::Object(right)) => {
-			for (key, value) in right {
-				merge_json_value(left.entry(key).or_insert(Value::Null), value);
-			}
-		}
-
-		(left, right) => *left = right,
-	}
-}
-
 lazy_static! {
 	pub static ref CACHE: Arc<Mutex<LRU>> = Arc::new(Mutex::new(LRU::new()));
 }
@@ -76,8 +39,39 @@ pub async fn fetch_v2<Q: Serialize, R: Serialize + DeserializeOwned>(
 		return Ok((response, true));
 	}
 
+	let response: R = match request_v2(&query, url).await {
+		Ok(response) => response,
+		Err(err) => return Err(err),
+	};
+
+	match to_string(&response) {
+		Ok(response) => {
+			cache.insert(cache_key, response);
+		}
+		Err(err) => {
+			handle_error(&err.into());
+		}
+	}
+
+	Ok((response, false))
+}
+
+/// Fetches data from the Canister v2 API without going through the LRU cache
+/// Used for datasets that are held in memory separately
+pub async fn fetch_v2_uncached<Q: Serialize, R: DeserializeOwned>(
+	query: Q,
+	url: &str,
+) -> Result<R, Response> {
+	request_v2(&query, url).await
+}
+
+/// Executes a request against the Canister v2 API and deserializes the response
+async fn request_v2<Q: Serialize, R: DeserializeOwned>(
+	query: &Q,
+	url: &str,
+) -> Result<R, Response> {
 	let url = format!("/v2{}", url);
-	let request = match canister().get(&url).query(&query) {
+	let request = match canister().get(&url).query(query) {
 		Ok(request) => request,
 		Err(err) => {
 			handle_error(&err.into_inner());
@@ -97,25 +91,10 @@ pub async fn fetch_v2<Q: Serialize, R: Serialize + DeserializeOwned>(
 	}
 
 	match response.status() {
-		StatusCode::Ok => {
-			let response: R = match response.body_json().await {
-				Ok(response) => response,
-				Err(_) => {
-					return Err(error_respond(500, "Failed to parse Canister response"));
-				}
-			};
-
-			match to_string(&response) {
-				Ok(response) => {
-					cache.insert(cache_key, response);
-				}
-				Err(err) => {
-					handle_error(&err.into());
-				}
-			}
-
-			Ok((response, false))
-		}
+		StatusCode::Ok => match response.body_json().await {
+			Ok(response) => Ok(response),
+			Err(_) => Err(error_respond(500, "Failed to parse Canister response")),
+		},
 
 		StatusCode::BadRequest | StatusCode::NotFound => {
 			let response: HTTPError = match response.body_json().await {
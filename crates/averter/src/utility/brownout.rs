@@ -0,0 +1,268 @@
+use super::{
+	deprecation_config, error_respond, increment_metric, is_v1_route, lock, parse_date, v2_url,
+	Request, Response,
+};
+use chrono::{DateTime, Datelike, Duration, Timelike, Utc};
+use lazy_static::lazy_static;
+use serde::Deserialize;
+use std::{
+	collections::hash_map::RandomState,
+	hash::{BuildHasher, Hasher},
+	sync::{
+		atomic::{AtomicU64, Ordering},
+		Mutex,
+	},
+};
+
+static ROLL_COUNTER: AtomicU64 = AtomicU64::new(0);
+
+lazy_static! {
+	static ref ROLL_STATE: RandomState = RandomState::new();
+}
+
+/// Longest window a cron brownout may last, in minutes
+const MAX_CRON_DURATION: i64 = 24 * 60;
+
+/// A planned window during which v1 routes answer with 410 Gone
+/// Windows are either explicit ('start' and 'end') or cron-like ('cron' and 'duration' in minutes)
+#[derive(Deserialize, Debug)]
+pub struct Brownout {
+	start: Option<String>,
+	end: Option<String>,
+	cron: Option<String>,
+	#[serde(default = "default_duration")]
+	duration: i64,
+	#[serde(default = "default_percentage")]
+	percentage: u8,
+	/// v1 paths affected by the brownout, every v1 route when empty
+	#[serde(default)]
+	routes: Vec<String>,
+	/// The minute a cron window was last checked for and whether it was active then
+	#[serde(skip)]
+	window: Mutex<Option<(i64, bool)>>,
+}
+
+fn default_duration() -> i64 {
+	60
+}
+
+fn default_percentage() -> u8 {
+	100
+}
+
+impl Brownout {
+	/// Checks that the brownout has either a valid interval or a valid cron schedule
+	pub fn is_valid(&self) -> bool {
+		let interval = match (&self.start, &self.end) {
+			(Some(_), Some(_)) => {
+				parse_date(self.start.as_ref()).is_some() && parse_date(self.end.as_ref()).is_some()
+			}
+			(None, None) => false,
+			_ => return false,
+		};
+
+		let cron = match &self.cron {
+			Some(cron) => cron_fields(cron).is_some(),
+			None => false,
+		};
+
+		interval != cron
+			&& self.percentage <= 100
+			&& (1..=MAX_CRON_DURATION).contains(&self.duration)
+	}
+
+	/// Returns the configured route that covers a path, or '*' for brownouts of every route
+	fn matching_route(&self, path: &str) -> Option<&str> {
+		if self.routes.is_empty() {
+			return Some("*");
+		}
+
+		self.routes
+			.iter()
+			.find(|route| *route == path)
+			.or_else(|| self.routes.iter().find(|route| *route == "*"))
+			.map(|route| route.as_str())
+	}
+
+	fn is_active(&self, now: DateTime<Utc>) -> bool {
+		if let Some(cron) = &self.cron {
+			// The window only changes once a minute, so the schedule is scanned at most that often
+			let minute = now.timestamp().div_euclid(60);
+			let mut window = lock(&self.window);
+
+			if let Some((checked, active)) = *window {
+				if checked == minute {
+					return active;
+				}
+			}
+
+			let active = cron_window_active(cron, self.duration, now);
+			*window = Some((minute, active));
+			return active;
+		}
+
+		match (
+			parse_date(self.start.as_ref()),
+			parse_date(self.end.as_ref()),
+		) {
+			(Some(start), Some(end)) => start <= now && now < end,
+			_ => false,
+		}
+	}
+}
+
+/// Checks if a cron window started within the last 'duration' minutes
+fn cron_window_active(cron: &str, duration: i64, now: DateTime<Utc>) -> bool {
+	let fields = match cron_fields(cron) {
+		Some(fields) => fields,
+		None => return false,
+	};
+
+	let now = now - Duration::seconds(now.second().into());
+	(0..duration).any(|offset| cron_matches(&fields, now - Duration::minutes(offset)))
+}
+
+/// Splits a cron expression into its five fields, validating each one
+fn cron_fields(cron: &str) -> Option<[&str; 5]> {
+	let fields = cron.split_whitespace().collect::<Vec<&str>>();
+	let fields: [&str; 5] = fields.try_into().ok()?;
+	let ranges = [(0, 59), (0, 23), (1, 31), (1, 12), (0, 7)];
+
+	for (field, (min, max)) in fields.iter().zip(ranges) {
+		for part in field.split(',') {
+			let range = match part.split_once('/') {
+				Some((range, step)) => {
+					step.parse::<u32>().ok().filter(|step| *step > 0)?;
+					range
+				}
+				None => part,
+			};
+
+			if range == "*" {
+				continue;
+			}
+
+			let (start, end) = match range.split_once('-') {
+				Some((start, end)) => (start.parse::<u32>().ok()?, end.parse::<u32>().ok()?),
+				None => (range.parse::<u32>().ok()?, range.parse::<u32>().ok()?),
+			};
+
+			if start < min || end > max || start > end {
+				return None;
+			}
+		}
+	}
+
+	Some(fields)
+}
+
+/// Checks if a cron field matches a value
+/// Supports '*', single values, ranges, lists and steps
+fn field_matches(field: &str, value: u32, min: u32, max: u32) -> bool {
+	field.split(',').any(|part| {
+		let (range, step) = match part.split_once('/') {
+			Some((range, step)) => (range, step.parse::<u32>().unwrap_or(1).max(1)),
+			None => (part, 1),
+		};
+
+		let (start, end) = match range {
+			"*" => (min, max),
+			range => match range.split_once('-') {
+				Some((start, end)) => (
+					start.parse::<u32>().unwrap_or(u32::MAX),
+					end.parse::<u32>().unwrap_or(0),
+				),
+				None => match range.parse::<u32>() {
+					Ok(start) if step > 1 => (start, max),
+					Ok(start) => (start, start),
+					Err(_) => return false,
+				},
+			},
+		};
+
+		(start..=end)
+			.step_by(step as usize)
+			.any(|item| item == value)
+	})
+}
+
+/// Checks if a cron schedule fires at the given minute
+/// Like standard cron, either day field may match when both are restricted
+fn cron_matches(fields: &[&str; 5], date: DateTime<Utc>) -> bool {
+	let weekday = date.weekday().num_days_from_sunday();
+	let day_matches = field_matches(fields[2], date.day(), 1, 31);
+	let weekday_matches = field_matches(fields[4], weekday, 0, 7)
+		|| (weekday == 0 && field_matches(fields[4], 7, 0, 7));
+
+	let days_match = match (fields[2].starts_with('*'), fields[4].starts_with('*')) {
+		(false, false) => day_matches || weekday_matches,
+		_ => day_matches && weekday_matches,
+	};
+
+	field_matches(fields[0], date.minute(), 0, 59)
+		&& field_matches(fields[1], date.hour(), 0, 23)
+		&& field_matches(fields[3], date.month(), 1, 12)
+		&& days_match
+}
+
+/// Rolls a random number against a percentage
+/// Each roll hashes a fresh counter value with a randomly keyed hasher
+fn roll(percentage: u8) -> bool {
+	match percentage {
+		0 => false,
+		100.. => true,
+		percentage => {
+			let mut hasher = ROLL_STATE.build_hasher();
+			hasher.write_u64(ROLL_COUNTER.fetch_add(1, Ordering::Relaxed));
+			hasher.finish() % 100 < u64::from(percentage)
+		}
+	}
+}
+
+/// Checks if the client is exempt from brownouts via the allowlist
+fn is_exempt(req: &Request) -> bool {
+	let user_agent = match req.headers().get("User-Agent") {
+		Some(user_agent) => user_agent.to_str().unwrap_or_default().to_lowercase(),
+		None => return false,
+	};
+
+	deprecation_config()
+		.allowlist
+		.iter()
+		.any(|agent| user_agent.contains(&agent.to_lowercase()))
+}
+
+/// Returns a 410 response if the request falls into an active brownout
+pub fn brownout_response(req: &Request) -> Option<Response> {
+	let config = deprecation_config();
+	if config.brownouts.is_empty() {
+		return None;
+	}
+
+	let path = req.path().trim_end_matches('/');
+	if !is_v1_route(path) {
+		return None;
+	}
+
+	let now = Utc::now();
+	let (brownout, route) = config.brownouts.iter().find_map(|brownout| {
+		match (brownout.matching_route(path), brownout.is_active(now)) {
+			(Some(route), true) => Some((brownout, route)),
+			_ => None,
+		}
+	})?;
+
+	if is_exempt(req) || !roll(brownout.percentage) {
+		return None;
+	}
+
+	increment_metric("brownouts");
+	// Keyed by the configured route so arbitrary paths can't add new counters
+	increment_metric(&format!("brownouts {route}"));
+
+	let successor = v2_url(req).unwrap_or_else(|| env!("CANISTER_DOCS_ENDPOINT").to_owned());
+	Some(error_respond(
+		410,
+		&format!("This route is unavailable during a scheduled brownout, migrate to {successor}"),
+	))
+}
@@ -0,0 +1,256 @@
+use super::{lock, LRU};
+use actix_web::{
+	body::{BoxBody, MessageBody},
+	dev::ServiceResponse,
+	http::header::{HeaderValue, CONTENT_ENCODING, CONTENT_TYPE, VARY},
+	web::Bytes,
+};
+use anyhow::Error;
+use flate2::{write::GzEncoder, Compression};
+use lazy_static::lazy_static;
+use std::{
+	collections::hash_map::DefaultHasher,
+	env::var,
+	hash::{Hash, Hasher},
+	io::Write,
+	sync::Mutex,
+};
+
+/// Bodies smaller than this are sent uncompressed unless 'AVERTER_COMPRESSION_MIN_SIZE' is set
+const DEFAULT_MIN_SIZE: usize = 1024;
+
+/// Number of pre-compressed bodies kept for replayed v2 passthrough responses
+const COMPRESSED_CACHE_SIZE: usize = 30;
+
+/// Content types worth compressing
+const COMPRESSIBLE_TYPES: &[&str] = &[
+	"application/json",
+	"application/x-ndjson",
+	"application/msgpack",
+	"text/",
+];
+
+/// A content coding supported by averter
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum Encoding {
+	Identity,
+	Gzip,
+	Brotli,
+	Zstd,
+}
+
+impl Encoding {
+	pub fn as_str(&self) -> &'static str {
+		match self {
+			Self::Identity => "identity",
+			Self::Gzip => "gzip",
+			Self::Brotli => "br",
+			Self::Zstd => "zstd",
+		}
+	}
+}
+
+/// Marks a response whose body is replayed byte for byte from cache, so its compressed variants are cached too
+/// v1 responses are never marked since their envelope carries a per-request 'date'
+pub struct CachedBody;
+
+lazy_static! {
+	static ref MIN_SIZE: usize = match var("AVERTER_COMPRESSION_MIN_SIZE") {
+		Ok(size) => size.parse().unwrap_or(DEFAULT_MIN_SIZE),
+		Err(_) => DEFAULT_MIN_SIZE,
+	};
+	static ref COMPRESSED: Mutex<LRU<Bytes>> =
+		Mutex::new(LRU::with_capacity(COMPRESSED_CACHE_SIZE));
+}
+
+/// Picks the preferred encoding from an 'Accept-Encoding' header
+/// The highest quality wins, with ties going to brotli, then zstd, then gzip
+/// Codings refused with 'q=0' are never picked, falling back to identity
+pub fn negotiate_encoding(accept_encoding: Option<&str>) -> Encoding {
+	let codings = accept_encoding
+		.unwrap_or_default()
+		.split(',')
+		.filter_map(|coding| {
+			let mut parts = coding.split(';').map(|part| part.trim());
+			let encodings: &[Encoding] = match parts.next()?.to_lowercase().as_str() {
+				"br" => &[Encoding::Brotli],
+				"zstd" => &[Encoding::Zstd],
+				"gzip" | "x-gzip" => &[Encoding::Gzip],
+				"*" => &[Encoding::Brotli, Encoding::Zstd, Encoding::Gzip],
+				_ => return None,
+			};
+
+			let quality = parts
+				.find_map(|part| part.strip_prefix("q="))
+				.and_then(|quality| quality.parse::<f32>().ok())
+				.unwrap_or(1.0);
+
+			Some((encodings, quality))
+		})
+		.collect::<Vec<(&[Encoding], f32)>>();
+
+	// '*' only covers codings the client didn't list by name
+	let is_named = |encoding: &Encoding| {
+		codings
+			.iter()
+			.any(|(encodings, _)| encodings.len() == 1 && encodings[0] == *encoding)
+	};
+
+	let mut best = (Encoding::Identity, 0.0);
+	for (encodings, quality) in &codings {
+		// A quality of zero means the client refuses the coding outright
+		if *quality <= 0.0 {
+			continue;
+		}
+
+		for encoding in encodings.iter() {
+			if encodings.len() > 1 && is_named(encoding) {
+				continue;
+			}
+
+			if *quality > best.1 || (*quality == best.1 && rank(*encoding) < rank(best.0)) {
+				best = (*encoding, *quality);
+			}
+		}
+	}
+
+	best.0
+}
+
+fn rank(encoding: Encoding) -> u8 {
+	match encoding {
+		Encoding::Brotli => 0,
+		Encoding::Zstd => 1,
+		Encoding::Gzip => 2,
+		Encoding::Identity => 3,
+	}
+}
+
+/// Compresses a body with the given encoding
+pub fn compress(body: &[u8], encoding: Encoding) -> Result<Vec<u8>, Error> {
+	match encoding {
+		Encoding::Identity => Ok(body.to_vec()),
+		Encoding::Gzip => {
+			let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+			encoder.write_all(body)?;
+			Ok(encoder.finish()?)
+		}
+
+		Encoding::Brotli => {
+			let mut output = Vec::new();
+			{
+				let mut encoder = brotli::CompressorWriter::new(&mut output, 4096, 5, 22);
+				encoder.write_all(body)?;
+			}
+
+			Ok(output)
+		}
+
+		Encoding::Zstd => Ok(zstd::encode_all(body, 3)?),
+	}
+}
+
+/// Compresses a response body if the client accepts it and the body is large enough
+/// Streamed bodies are passed through untouched since their size isn't known upfront
+pub fn compress_response(res: ServiceResponse, encoding: Encoding) -> ServiceResponse {
+	let is_compressible = match res.headers().get(CONTENT_TYPE) {
+		Some(content_type) => {
+			let content_type = content_type.to_str().unwrap_or_default();
+			COMPRESSIBLE_TYPES
+				.iter()
+				.any(|prefix| content_type.starts_with(prefix))
+		}
+		None => false,
+	};
+
+	if !is_compressible || res.headers().contains_key(CONTENT_ENCODING) {
+		return res;
+	}
+
+	let is_cached = res.response().extensions().contains::<CachedBody>();
+	let (req, mut res) = res.into_parts();
+	// Merged into a single header since later middleware only reads the first 'Vary' value
+	let vary = match res.headers().get(VARY).and_then(|vary| vary.to_str().ok()) {
+		Some(vary) => HeaderValue::from_str(&format!("{vary}, Accept-Encoding")),
+		None => Ok(HeaderValue::from_static("Accept-Encoding")),
+	};
+
+	if let Ok(vary) = vary {
+		res.headers_mut().insert(VARY, vary);
+	}
+
+	let (res, body) = res.into_parts();
+	let body = match body.try_into_bytes() {
+		Ok(body) => body,
+		Err(body) => return ServiceResponse::new(req, res.set_body(body)),
+	};
+
+	if encoding == Encoding::Identity || body.len() < *MIN_SIZE {
+		return ServiceResponse::new(req, res.set_body(BoxBody::new(body)));
+	}
+
+	let compressed = match is_cached {
+		true => cached_compress(&body, encoding),
+		false => compress(&body, encoding).map(Bytes::from),
+	};
+
+	match compressed {
+		Ok(compressed) => {
+			let mut res = res.set_body(BoxBody::new(compressed));
+			res.headers_mut().insert(
+				CONTENT_ENCODING,
+				HeaderValue::from_static(encoding.as_str()),
+			);
+
+			ServiceResponse::new(req, res)
+		}
+
+		// Falling back to the uncompressed body is always safe
+		Err(_) => ServiceResponse::new(req, res.set_body(BoxBody::new(body))),
+	}
+}
+
+/// Compresses a cached body, reusing the compressed variant from earlier responses
+/// Variants are keyed by a hash of the body so a refreshed cache entry is never served stale
+fn cached_compress(body: &Bytes, encoding: Encoding) -> Result<Bytes, Error> {
+	let mut hasher = DefaultHasher::new();
+	body.hash(&mut hasher);
+	let key = format!("{:x}:{}", hasher.finish(), encoding.as_str());
+
+	let cached = lock(&COMPRESSED).get(key.clone());
+
+	if let Some(compressed) = cached {
+		return Ok(compressed);
+	}
+
+	let compressed = Bytes::from(compress(body, encoding)?);
+	lock(&COMPRESSED).insert(key, compressed.clone());
+
+	Ok(compressed)
+}
+
+#[cfg(test)]
+mod tests {
+	use super::{negotiate_encoding, Encoding};
+
+	#[test]
+	fn negotiate_encoding_skips_refused_codings() {
+		let cases = [
+			(None, Encoding::Identity),
+			(Some("gzip, br"), Encoding::Brotli),
+			(Some("gzip;q=1, br;q=0.5"), Encoding::Gzip),
+			(Some("br;q=0"), Encoding::Identity),
+			(Some("*;q=0"), Encoding::Identity),
+			(Some("gzip;q=0, identity"), Encoding::Identity),
+			(Some("br;q=0, *"), Encoding::Zstd),
+		];
+
+		for (accept_encoding, expected) in cases {
+			assert!(
+				negotiate_encoding(accept_encoding) == expected,
+				"{accept_encoding:?} should negotiate {}",
+				expected.as_str()
+			);
+		}
+	}
+}
@@ -0,0 +1,53 @@
+use super::{negotiate, v2_url, Negotiation, Request};
+use std::future::Future;
+use tokio::task::futures::TaskLocalFuture;
+
+tokio::task_local! {
+	static CONTEXT: RequestContext;
+}
+
+/// Details about the request being handled
+/// Lets response helpers tailor output without threading the request through every route
+#[derive(Clone)]
+pub struct RequestContext {
+	pub path: String,
+	pub user_agent: Option<String>,
+	pub v2_url: Option<String>,
+	/// Set when the client acknowledged the notice with 'X-Averter-Notice: acknowledged'
+	pub hide_notice: bool,
+	pub negotiation: Negotiation,
+}
+
+impl RequestContext {
+	pub fn from_request(req: &Request) -> Self {
+		Self {
+			path: req.path().trim_end_matches('/').to_owned(),
+			user_agent: req
+				.headers()
+				.get("User-Agent")
+				.and_then(|value| value.to_str().ok())
+				.map(|value| value.to_owned()),
+			v2_url: v2_url(req),
+			hide_notice: matches!(
+				req.headers().get("X-Averter-Notice").map(|value| value.to_str()),
+				Some(Ok(value)) if value.eq_ignore_ascii_case("acknowledged")
+			),
+			negotiation: negotiate(req).unwrap_or_default(),
+		}
+	}
+}
+
+/// Calls a service with the given request context
+/// The context is set both while the call is made and while its future is polled
+pub fn with_context<F: Future>(
+	context: RequestContext,
+	call: impl FnOnce() -> F,
+) -> TaskLocalFuture<RequestContext, F> {
+	let future = CONTEXT.sync_scope(context.clone(), call);
+	CONTEXT.scope(context, future)
+}
+
+/// Returns the context of the request being handled, if any
+pub fn current_context() -> Option<RequestContext> {
+	CONTEXT.try_with(|context| context.clone()).ok()
+}
@@ -0,0 +1,127 @@
+use super::{load_json_config, v2_url, Brownout, Request};
+use chrono::{DateTime, NaiveDate, TimeZone, Utc};
+use lazy_static::lazy_static;
+use serde::Deserialize;
+use std::collections::HashMap;
+
+#[derive(Deserialize, Default)]
+pub(super) struct DeprecationConfig {
+	deprecated: Option<String>,
+	sunset: Option<String>,
+	#[serde(default)]
+	routes: HashMap<String, RoutePolicy>,
+	#[serde(default)]
+	pub(super) brownouts: Vec<Brownout>,
+	/// User-Agent substrings of clients exempt from brownouts
+	#[serde(default)]
+	pub(super) allowlist: Vec<String>,
+}
+
+#[derive(Deserialize)]
+struct RoutePolicy {
+	deprecated: Option<String>,
+	sunset: Option<String>,
+}
+
+/// When a v1 route was deprecated and when it will stop working
+pub struct Deprecation {
+	pub deprecated: Option<DateTime<Utc>>,
+	pub sunset: Option<DateTime<Utc>>,
+}
+
+lazy_static! {
+	/// Deprecation policy loaded from the JSON file at 'AVERTER_DEPRECATION_CONFIG'
+	static ref CONFIG: DeprecationConfig = load_config();
+}
+
+fn load_config() -> DeprecationConfig {
+	let config: DeprecationConfig = load_json_config("AVERTER_DEPRECATION_CONFIG", "deprecation");
+	if let Some(brownout) = config
+		.brownouts
+		.iter()
+		.find(|brownout| !brownout.is_valid())
+	{
+		panic!("Invalid brownout in deprecation config ({brownout:?})");
+	}
+
+	config
+}
+
+/// Returns the deprecation policy loaded at startup
+pub(super) fn deprecation_config() -> &'static DeprecationConfig {
+	&CONFIG
+}
+
+/// Parses a date as either 'YYYY-MM-DD' or RFC 3339
+pub(super) fn parse_date(date: Option<&String>) -> Option<DateTime<Utc>> {
+	let date = date?;
+	if let Ok(date) = DateTime::parse_from_rfc3339(date) {
+		return Some(date.with_timezone(&Utc));
+	}
+
+	NaiveDate::parse_from_str(date, "%Y-%m-%d")
+		.ok()
+		.and_then(|date| date.and_hms_opt(0, 0, 0))
+		.map(|date| Utc.from_utc_datetime(&date))
+}
+
+/// Checks if a path is a v1 route served by averter
+pub fn is_v1_route(path: &str) -> bool {
+	path.starts_with("/community")
+}
+
+/// Returns the deprecation policy for a v1 route
+/// Route-specific dates take precedence over the global dates
+pub fn route_deprecation(path: &str) -> Deprecation {
+	if !is_v1_route(path) {
+		return Deprecation {
+			deprecated: None,
+			sunset: None,
+		};
+	}
+
+	let route = CONFIG.routes.get(path);
+	Deprecation {
+		deprecated: parse_date(
+			route
+				.and_then(|route| route.deprecated.as_ref())
+				.or(CONFIG.deprecated.as_ref()),
+		),
+		sunset: parse_date(
+			route
+				.and_then(|route| route.sunset.as_ref())
+				.or(CONFIG.sunset.as_ref()),
+		),
+	}
+}
+
+/// Builds the RFC 8594 'Deprecation', 'Sunset' and 'Link' headers for a v1 request
+/// Header names are lowercase so they can be used as static header names
+pub fn deprecation_headers(req: &Request) -> Vec<(&'static str, String)> {
+	let path = req.path().trim_end_matches('/');
+	if !is_v1_route(path) {
+		return Vec::new();
+	}
+
+	let deprecation = route_deprecation(path);
+	let mut headers = vec![(
+		"deprecation",
+		match deprecation.deprecated {
+			Some(date) => http_date(date),
+			None => "true".to_owned(),
+		},
+	)];
+
+	if let Some(sunset) = deprecation.sunset {
+		headers.push(("sunset", http_date(sunset)));
+	}
+
+	let successor = v2_url(req).unwrap_or_else(|| env!("CANISTER_DOCS_ENDPOINT").to_owned());
+	headers.push(("link", format!("<{successor}>; rel=\"successor-version\"")));
+	headers
+}
+
+/// Formats a date as an IMF-fixdate for HTTP headers
+pub fn http_date(date: DateTime<Utc>) -> String {
+	date.format("%a, %d %b %Y %H:%M:%S GMT").to_string()
+}
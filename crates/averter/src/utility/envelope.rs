@@ -0,0 +1,92 @@
+use serde::Serialize;
+use serde_json::{to_value, Map, Value};
+
+/// Keys owned by the envelope that route-specific fields may not use
+const RESERVED_KEYS: &[&str] = &["notice", "message", "date", "error", "pagination", "data"];
+
+/// Pagination details for routes that return a page of results
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Pagination {
+	pub page: u32,
+	pub limit: u32,
+	pub has_next: bool,
+}
+
+/// The v1 response envelope
+/// 'notice', 'message' and 'date' are always filled in when responding, so routes can't omit or overwrite them
+#[derive(Serialize)]
+pub struct Envelope<T: Serialize = Value> {
+	#[serde(skip_serializing_if = "Option::is_none")]
+	pub(super) notice: Option<Value>,
+	pub(super) message: String,
+	pub(super) date: String,
+	#[serde(skip_serializing_if = "Option::is_none")]
+	error: Option<String>,
+	#[serde(skip_serializing_if = "Option::is_none")]
+	pagination: Option<Pagination>,
+	#[serde(flatten)]
+	fields: Map<String, Value>,
+	#[serde(skip_serializing_if = "Option::is_none")]
+	data: Option<T>,
+}
+
+impl<T: Serialize> Envelope<T> {
+	/// Creates an envelope carrying the given data
+	pub fn new(data: T) -> Self {
+		Self {
+			data: Some(data),
+			..Default::default()
+		}
+	}
+
+	/// Adds pagination details to the envelope
+	pub fn pagination(mut self, pagination: Pagination) -> Self {
+		self.pagination = Some(pagination);
+		self
+	}
+
+	/// Adds a route-specific field to the envelope
+	/// Fields that clash with the envelope's own keys are ignored
+	pub fn field<V: Serialize>(mut self, key: &str, value: V) -> Self {
+		debug_assert!(!RESERVED_KEYS.contains(&key), "'{key}' is reserved");
+		if RESERVED_KEYS.contains(&key) {
+			return self;
+		}
+
+		if let Ok(value) = to_value(value) {
+			self.fields.insert(key.to_owned(), value);
+		}
+
+		self
+	}
+}
+
+impl Envelope {
+	/// Creates an envelope with no data
+	pub fn empty() -> Self {
+		Self::default()
+	}
+
+	/// Creates an envelope carrying an error message and no data
+	pub fn error(message: &str) -> Self {
+		Self {
+			error: Some(message.to_owned()),
+			..Default::default()
+		}
+	}
+}
+
+impl<T: Serialize> Default for Envelope<T> {
+	fn default() -> Self {
+		Self {
+			notice: None,
+			message: String::new(),
+			date: String::new(),
+			error: None,
+			pagination: None,
+			fields: Map::new(),
+			data: None,
+		}
+	}
+}
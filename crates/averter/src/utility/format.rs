@@ -0,0 +1,246 @@
+use super::{error_respond, is_v1_route, Request, Response};
+use actix_web::web::Bytes;
+use anyhow::Error;
+use serde::Serialize;
+use serde_json::{to_string, to_string_pretty, to_value, to_vec, Map, Value};
+use url::form_urlencoded::parse;
+
+/// A response format the client can ask for
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum Format {
+	Json,
+	Ndjson,
+	Csv,
+	MessagePack,
+}
+
+impl Format {
+	fn from_name(name: &str) -> Option<Self> {
+		match name.to_lowercase().as_str() {
+			"json" => Some(Self::Json),
+			"ndjson" => Some(Self::Ndjson),
+			"csv" => Some(Self::Csv),
+			"msgpack" | "messagepack" => Some(Self::MessagePack),
+			_ => None,
+		}
+	}
+
+	fn from_media_type(media_type: &str) -> Option<Self> {
+		match media_type.to_lowercase().as_str() {
+			"application/json" | "application/*" | "*/*" => Some(Self::Json),
+			"application/x-ndjson" | "application/ndjson" => Some(Self::Ndjson),
+			"text/csv" => Some(Self::Csv),
+			"application/msgpack" | "application/x-msgpack" | "application/vnd.msgpack" => {
+				Some(Self::MessagePack)
+			}
+			_ => None,
+		}
+	}
+
+	pub fn content_type(&self) -> &'static str {
+		match self {
+			Self::Json => "application/json",
+			Self::Ndjson => "application/x-ndjson",
+			Self::Csv => "text/csv; charset=utf-8",
+			Self::MessagePack => "application/msgpack",
+		}
+	}
+}
+
+/// How the client wants the response body rendered
+#[derive(Clone, Copy)]
+pub struct Negotiation {
+	pub format: Format,
+	pub pretty: bool,
+}
+
+impl Default for Negotiation {
+	fn default() -> Self {
+		Self {
+			format: Format::Json,
+			pretty: false,
+		}
+	}
+}
+
+/// A rendered response body
+pub enum Rendered {
+	Whole(Vec<u8>),
+	/// One NDJSON line per row, each serialized only when the client is ready for it
+	Lines(Vec<Value>),
+}
+
+/// Picks the response format from the 'format' parameter, falling back to the 'Accept' header
+/// Unknown 'format' values are rejected, while unknown media types fall back to JSON
+pub fn negotiate(req: &Request) -> Result<Negotiation, String> {
+	let mut format = None;
+	let mut pretty = false;
+	for (key, value) in parse(req.query_string().as_bytes()) {
+		match key.as_ref() {
+			"format" => match Format::from_name(&value) {
+				Some(value) => format = Some(value),
+				None => return Err(value.into_owned()),
+			},
+			"pretty" => pretty = value == "true",
+			_ => (),
+		}
+	}
+
+	let accept = req
+		.headers()
+		.get("Accept")
+		.and_then(|value| value.to_str().ok())
+		.unwrap_or_default();
+
+	// Media types are taken in the order given, skipping any the client refuses with 'q=0'
+	let format = format.or_else(|| {
+		accept.split(',').find_map(|media_type| {
+			let mut parts = media_type.split(';').map(|part| part.trim());
+			let format = Format::from_media_type(parts.next()?)?;
+			match parts.any(|part| part.replace(' ', "") == "q=0") {
+				true => None,
+				false => Some(format),
+			}
+		})
+	});
+
+	Ok(Negotiation {
+		format: format.unwrap_or(Format::Json),
+		pretty,
+	})
+}
+
+/// Returns a 400 response if a v1 request asks for an unknown format
+/// Other routes, like the v2 passthrough, may use 'format' for their own purposes
+pub fn invalid_format_response(req: &Request) -> Option<Response> {
+	if !is_v1_route(req.path()) {
+		return None;
+	}
+
+	match negotiate(req) {
+		Ok(_) => None,
+		Err(format) => Some(error_respond(
+			400,
+			&format!("Invalid format: \'{format}\', expected \'json\', \'ndjson\', \'csv\' or \'msgpack\'"),
+		)),
+	}
+}
+
+/// Renders a body in the negotiated format
+/// NDJSON and CSV render the 'data' field and fall back to JSON for bodies without one
+pub fn render<T: Serialize>(
+	body: &T,
+	negotiation: Negotiation,
+) -> Result<(Format, Rendered), Error> {
+	let rows = match negotiation.format {
+		Format::Ndjson | Format::Csv => match to_value(body)?.get_mut("data").map(Value::take) {
+			Some(Value::Array(items)) => Some(items),
+			Some(Value::Null) | None => None,
+			Some(item) => Some(vec![item]),
+		},
+		_ => None,
+	};
+
+	match (negotiation.format, rows) {
+		(Format::Ndjson, Some(rows)) => Ok((Format::Ndjson, Rendered::Lines(rows))),
+		(Format::Csv, Some(rows)) => Ok((Format::Csv, Rendered::Whole(csv(&rows).into_bytes()))),
+		(Format::MessagePack, _) => Ok((
+			Format::MessagePack,
+			Rendered::Whole(rmp_serde::to_vec_named(body)?),
+		)),
+
+		_ => {
+			let body = match negotiation.pretty {
+				true => to_string_pretty(body)?,
+				false => to_string(body)?,
+			};
+
+			Ok((Format::Json, Rendered::Whole(body.into_bytes())))
+		}
+	}
+}
+
+/// Serializes a row as a single NDJSON line
+pub fn ndjson_line(row: &Value) -> Result<Bytes, serde_json::Error> {
+	let mut line = to_vec(row)?;
+	line.push(b'\n');
+	Ok(Bytes::from(line))
+}
+
+/// Writes rows as CSV, flattening nested objects into dotted columns
+/// Columns are ordered by first appearance across all rows
+fn csv(rows: &[Value]) -> String {
+	let rows = rows
+		.iter()
+		.map(|row| {
+			let mut columns = Map::new();
+			flatten("", row, &mut columns);
+			columns
+		})
+		.collect::<Vec<Map<String, Value>>>();
+
+	let mut header: Vec<&str> = Vec::new();
+	for row in &rows {
+		for key in row.keys() {
+			if !header.contains(&key.as_str()) {
+				header.push(key);
+			}
+		}
+	}
+
+	let mut output = header
+		.iter()
+		.map(|column| csv_field(column))
+		.collect::<Vec<String>>()
+		.join(",");
+
+	output.push_str("\r\n");
+	for row in &rows {
+		let line = header
+			.iter()
+			.map(|column| match row.get(*column) {
+				Some(Value::String(value)) => csv_field(value),
+				Some(Value::Null) | None => String::new(),
+				Some(value) => csv_field(&value.to_string()),
+			})
+			.collect::<Vec<String>>()
+			.join(",");
+
+		output.push_str(&line);
+		output.push_str("\r\n");
+	}
+
+	output
+}
+
+fn flatten(prefix: &str, value: &Value, columns: &mut Map<String, Value>) {
+	match value {
+		Value::Object(object) => {
+			for (key, value) in object {
+				let key = match prefix.is_empty() {
+					true => key.clone(),
+					false => format!("{prefix}.{key}"),
+				};
+
+				flatten(&key, value, columns);
+			}
+		}
+
+		value => {
+			let key = match prefix.is_empty() {
+				true => "value".to_owned(),
+				false => prefix.to_owned(),
+			};
+
+			columns.insert(key, value.clone());
+		}
+	}
+}
+
+/// Quotes a CSV field if it contains a delimiter, quote or newline
+fn csv_field(value: &str) -> String {
+	match value.contains([',', '"', '\n', '\r']) {
+		true => format!("\"{}\"", value.replace('"', "\"\"")),
+		false => value.to_owned(),
+	}
+}
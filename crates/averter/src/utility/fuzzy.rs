@@ -0,0 +1,76 @@
+use std::{collections::HashMap, mem::swap};
+
+/// Scores how closely a candidate matches a query, from 0.0 to 1.0
+/// Exact, prefix and substring matches score highest, then bigram similarity
+pub fn fuzzy_score(query: &str, candidate: &str) -> f64 {
+	let query = query.trim().to_lowercase();
+	let candidate = candidate.trim().to_lowercase();
+
+	if query.is_empty() || candidate.is_empty() {
+		return 0.0;
+	}
+
+	if candidate == query {
+		return 1.0;
+	}
+
+	if candidate.starts_with(&query) {
+		return 0.9;
+	}
+
+	if candidate.contains(&query) {
+		return 0.75;
+	}
+
+	bigram_similarity(&query, &candidate) * 0.7
+}
+
+/// Computes the Levenshtein edit distance between two strings
+pub fn edit_distance(left: &str, right: &str) -> usize {
+	let right = right.chars().collect::<Vec<char>>();
+	let mut previous = (0..=right.len()).collect::<Vec<usize>>();
+	let mut current = vec![0; right.len() + 1];
+
+	for (i, left_char) in left.chars().enumerate() {
+		current[0] = i + 1;
+		for (j, right_char) in right.iter().enumerate() {
+			let substitution = previous[j] + usize::from(left_char != *right_char);
+			current[j + 1] = substitution.min(previous[j + 1] + 1).min(current[j] + 1);
+		}
+
+		swap(&mut previous, &mut current);
+	}
+
+	previous[right.len()]
+}
+
+/// Computes the Sørensen–Dice coefficient over character bigrams
+pub fn bigram_similarity(left: &str, right: &str) -> f64 {
+	let left = bigrams(left);
+	let right = bigrams(right);
+
+	let total = left.values().sum::<usize>() + right.values().sum::<usize>();
+	if total == 0 {
+		return 0.0;
+	}
+
+	let shared = left
+		.iter()
+		.map(|(bigram, count)| match right.get(bigram) {
+			Some(other) => *count.min(other),
+			None => 0,
+		})
+		.sum::<usize>();
+
+	(2 * shared) as f64 / total as f64
+}
+
+fn bigrams(value: &str) -> HashMap<(char, char), usize> {
+	let chars = value.chars().collect::<Vec<char>>();
+	let mut bigrams = HashMap::new();
+	for window in chars.windows(2) {
+		*bigrams.entry((window[0], window[1])).or_insert(0) += 1;
+	}
+
+	bigrams
+}
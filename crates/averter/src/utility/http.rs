@@ -1,11 +1,16 @@
 use std::fmt::{Display, Formatter};
 
-use super::{handle_error, merge_json};
+use super::{
+	current_context, handle_error, ndjson_line, notice_varies_by_user_agent, render, render_notice,
+	Envelope, Negotiation, Rendered,
+};
 use actix_web::{HttpRequest, HttpResponse, HttpResponseBuilder, ResponseError};
 use anyhow::Error;
 use chrono::Utc;
+use futures_util::stream::iter;
 use http::StatusCode;
-use serde_json::{json, to_string_pretty, Value};
+use serde::Serialize;
+use serde_json::{json, Value};
 
 pub type Request = HttpRequest;
 pub type Response = Result<HttpResponse, InternalServerError>;
@@ -33,42 +38,32 @@ impl ResponseError for InternalServerError {
 }
 
 /// Returns a response with the given status code and body
-fn respond(status_code: u16, mut body: Value, should_merge: bool, is_cached: bool) -> Response {
-	let status = match StatusCode::from_u16(status_code) {
-		Ok(status) => status,
-		Err(err) => {
-			let anyhow: Error = err.into();
-			handle_error(&anyhow);
-			return Err(InternalServerError {});
-		}
+/// The body is rendered in the format negotiated by the client
+fn respond<T: Serialize>(status: StatusCode, body: &T, is_cached: bool) -> Response {
+	let negotiation = match current_context() {
+		Some(context) => context.negotiation,
+		None => Negotiation::default(),
 	};
 
-	if should_merge {
-		body = merge_json(
-			json!({
-				"notice": {
-					"api": env!("CANISTER_NOTICE_API"),
-					"data": env!("CANISTER_NOTICE_DATA"),
-					"migration": env!("CANISTER_NOTICE_MIGRATION").replace("{{docs}}", env!("CANISTER_DOCS_ENDPOINT")),
-				},
-				"message": format!("{status_code} {}", status.canonical_reason().unwrap_or("Unknown")),
-				"date": Utc::now().to_rfc3339()
-			}),
-			body,
-		);
-	}
-
-	let body = match to_string_pretty(&body) {
-		Ok(body) => body,
+	let (format, body) = match render(body, negotiation) {
+		Ok(rendered) => rendered,
 		Err(err) => {
-			let anyhow: Error = err.into();
-			handle_error(&anyhow);
+			handle_error(&err);
 			return Err(InternalServerError {});
 		}
 	};
 
-	Ok(HttpResponseBuilder::new(status)
-		.content_type("application/json")
+	let mut response = HttpResponseBuilder::new(status);
+	response
+		.content_type(format.content_type())
+		.append_header((
+			"Vary",
+			if notice_varies_by_user_agent() {
+				"Accept, X-Architecture, X-Averter-Notice, User-Agent"
+			} else {
+				"Accept, X-Architecture, X-Averter-Notice"
+			},
+		))
 		.append_header((
 			"Cache-Control",
 			if is_cached {
@@ -76,22 +71,65 @@ fn respond(status_code: u16, mut body: Value, should_merge: bool, is_cached: boo
 			} else {
 				"no-cache"
 			},
-		))
-		.body(body))
+		));
+
+	match body {
+		Rendered::Whole(body) => Ok(response.body(body)),
+		Rendered::Lines(rows) => {
+			Ok(response.streaming(iter(rows.into_iter().map(|row| ndjson_line(&row)))))
+		}
+	}
+}
+
+/// Parses a status code, reporting invalid ones
+fn status(status_code: u16) -> Result<StatusCode, InternalServerError> {
+	match StatusCode::from_u16(status_code) {
+		Ok(status) => Ok(status),
+		Err(err) => {
+			let anyhow: Error = err.into();
+			handle_error(&anyhow);
+			Err(InternalServerError {})
+		}
+	}
 }
 
 /// Returns a response with the given status code and body
 pub fn http_respond(status_code: u16, body: Value) -> Response {
-	respond(status_code, body, false, false)
+	respond(status(status_code)?, &body, false)
 }
 
-/// Returns a response with the given status code and body
-/// The body is merged with a date and status message
-pub fn api_respond(status_code: u16, is_cached: bool, body: Value) -> Response {
-	respond(status_code, body, true, is_cached)
+/// Returns a response with the given status code and envelope
+/// The envelope is filled in with the notice, date and status message
+pub fn api_respond<T: Serialize>(
+	status_code: u16,
+	is_cached: bool,
+	mut envelope: Envelope<T>,
+) -> Response {
+	let status = status(status_code)?;
+	envelope.notice = render_notice(current_context().as_ref());
+	envelope.message = format!(
+		"{status_code} {}",
+		status.canonical_reason().unwrap_or("Unknown")
+	);
+
+	envelope.date = Utc::now().to_rfc3339();
+	respond(status, &envelope, is_cached)
 }
 
 /// Returns a response with the given status code and error message
 pub fn error_respond(status_code: u16, message: &str) -> Response {
-	api_respond(status_code, false, json!({ "error": message }))
+	api_respond(status_code, false, Envelope::error(message))
+}
+
+/// Parses an optional boolean query parameter, accepting 'true' or 'false'
+pub fn bool_param(value: Option<&str>, name: &str) -> Result<Option<bool>, Response> {
+	match value {
+		Some("true") => Ok(Some(true)),
+		Some("false") => Ok(Some(false)),
+		Some(_) => Err(error_respond(
+			400,
+			&format!("Invalid query parameter: \'{name}\'"),
+		)),
+		None => Ok(None),
+	}
 }
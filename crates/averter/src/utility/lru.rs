@@ -3,20 +3,26 @@ use std::collections::{HashMap, VecDeque};
 const LRU_MAX_SIZE: usize = 10;
 
 #[derive(Debug)]
-pub struct LRU {
-	entries: HashMap<String, String>,
+pub struct LRU<V = String> {
+	entries: HashMap<String, V>,
 	queue: VecDeque<String>,
+	capacity: usize,
 }
 
-impl LRU {
+impl<V: Clone> LRU<V> {
 	pub fn new() -> Self {
+		Self::with_capacity(LRU_MAX_SIZE)
+	}
+
+	pub fn with_capacity(capacity: usize) -> Self {
 		Self {
-			entries: HashMap::with_capacity(LRU_MAX_SIZE),
-			queue: VecDeque::with_capacity(LRU_MAX_SIZE),
+			entries: HashMap::with_capacity(capacity),
+			queue: VecDeque::with_capacity(capacity),
+			capacity,
 		}
 	}
 
-	pub fn get(&mut self, key: String) -> Option<String> {
+	pub fn get(&mut self, key: String) -> Option<V> {
 		if !self.entries.contains_key(&key) {
 			return None;
 		}
@@ -40,7 +46,7 @@ impl LRU {
 		self.entries.get(&key).cloned()
 	}
 
-	pub fn insert(&mut self, key: String, value: String) {
+	pub fn insert(&mut self, key: String, value: V) {
 		if cfg!(debug_assertions) {
 			println!("cache -> SET {}", key);
 		}
@@ -54,7 +60,7 @@ impl LRU {
 
 		// Pops from the queue and removes the entry from the map
 		// Only runs if the queue is larger than the max size
-		if self.queue.len() > LRU_MAX_SIZE {
+		if self.queue.len() > self.capacity {
 			if let Some(key) = self.queue.pop_back() {
 				if cfg!(debug_assertions) {
 					println!("cache -> POP {key}");
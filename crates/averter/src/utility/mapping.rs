@@ -0,0 +1,161 @@
+use super::{
+	api_respond, error_respond, fetch_v2, project, requested_projection, Envelope, Request,
+	Response,
+};
+use actix_web::web::{get, Query, ServiceConfig};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Map, Value};
+use std::collections::{BTreeMap, HashMap};
+
+/// A v1 field and where its value comes from in a v2 object
+pub enum Field {
+	/// Copies a v2 field into a v1 field (v2 name, v1 name)
+	Rename(&'static str, &'static str),
+	/// Maps a nested v2 object with its own field mapping (v2 name, v1 name, fields)
+	Nested(&'static str, &'static str, &'static [Field]),
+	/// Computes a v1 field from the whole v2 object (v1 name, function)
+	Computed(&'static str, fn(&Value) -> Value),
+}
+
+/// A v1 query parameter and how it is forwarded to v2
+pub enum Param {
+	/// Forwarded as a v2 query parameter (v1 name, v2 name)
+	Query(&'static str, &'static str),
+	/// Only passed to the route's hook and never forwarded (v1 name)
+	Local(&'static str),
+}
+
+/// Filters or prepares the v2 objects before they are mapped
+/// The hook receives the values of the route's 'Param::Local' parameters
+pub type Hook =
+	fn(&Request, &HashMap<&'static str, String>, Vec<Value>) -> Result<Vec<Value>, Response>;
+
+/// A declarative mapping from a v1 route to a v2 endpoint
+pub struct RouteMapping {
+	pub path: &'static str,
+	pub endpoint: &'static str,
+	pub params: &'static [Param],
+	pub required: &'static [&'static str],
+	pub fields: &'static [Field],
+	/// Wraps each object as '{ "package": <v2 key>, "fields": [object] }' when set
+	pub group: Option<&'static str>,
+	pub hook: Option<Hook>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct CanisterResponse {
+	data: Value,
+}
+
+/// Maps a v2 object into a v1 object using a field mapping
+/// Missing v2 fields are mapped to null so the v1 shape is always complete
+pub fn map_fields(value: &Value, fields: &[Field]) -> Value {
+	let mut mapped = Map::new();
+	for field in fields {
+		match field {
+			Field::Rename(from, to) => {
+				let value = value.get(from).cloned().unwrap_or(Value::Null);
+				mapped.insert(to.to_string(), value);
+			}
+
+			Field::Nested(from, to, fields) => {
+				let value = match value.get(from) {
+					Some(Value::Null) | None => Value::Null,
+					Some(value) => map_fields(value, fields),
+				};
+
+				mapped.insert(to.to_string(), value);
+			}
+
+			Field::Computed(to, compute) => {
+				mapped.insert(to.to_string(), compute(value));
+			}
+		}
+	}
+
+	Value::Object(mapped)
+}
+
+/// Registers a handler for every route mapping
+pub fn configure_mappings(config: &mut ServiceConfig, mappings: &'static [RouteMapping]) {
+	for mapping in mappings {
+		config.route(
+			mapping.path,
+			get().to(move |req| mapped_route(req, mapping)),
+		);
+	}
+}
+
+/// Handles a v1 request by forwarding it to v2 and reshaping the response
+async fn mapped_route(req: Request, mapping: &'static RouteMapping) -> Response {
+	let params = match Query::<HashMap<String, String>>::from_query(req.query_string()) {
+		Ok(query) => query.into_inner(),
+		Err(_) => return error_respond(400, "Invalid query parameters"),
+	};
+
+	if let Some(missing) = mapping
+		.required
+		.iter()
+		.find(|name| !params.contains_key(**name))
+	{
+		return error_respond(400, &format!("Missing query parameter: \'{missing}\'"));
+	}
+
+	let projection = match requested_projection(&req, mapping.fields, &[]) {
+		Ok(projection) => projection,
+		Err(err) => return err,
+	};
+
+	// A sorted map keeps the cache key stable regardless of parameter order
+	let mut query: BTreeMap<&str, &str> = BTreeMap::new();
+	let mut locals: HashMap<&'static str, String> = HashMap::new();
+	for param in mapping.params {
+		match param {
+			Param::Query(from, to) => {
+				if let Some(value) = params.get(*from) {
+					query.insert(to, value);
+				}
+			}
+
+			Param::Local(name) => {
+				if let Some(value) = params.get(*name) {
+					locals.insert(name, value.clone());
+				}
+			}
+		}
+	}
+
+	let (response, is_cached) =
+		match fetch_v2::<BTreeMap<&str, &str>, CanisterResponse>(query, mapping.endpoint).await {
+			Ok(response) => response,
+			Err(err) => return err,
+		};
+
+	let items = match response.data {
+		Value::Array(items) => items,
+		Value::Null => Vec::new(),
+		item => vec![item],
+	};
+
+	let items = match mapping.hook {
+		Some(hook) => match hook(&req, &locals, items) {
+			Ok(items) => items,
+			Err(err) => return err,
+		},
+		None => items,
+	};
+
+	let data = items.iter().map(|item| {
+		let fields = project(projection.as_ref(), map_fields(item, mapping.fields));
+		match mapping.group {
+			Some(key) => json!({
+				"package": item.get(key),
+				"fields": vec![fields],
+			}),
+			None => fields,
+		}
+	});
+
+	let data = data.collect::<Vec<Value>>();
+	api_respond(200, is_cached, Envelope::new(data))
+}
@@ -0,0 +1,19 @@
+use super::lock;
+use lazy_static::lazy_static;
+use std::{collections::BTreeMap, sync::Mutex};
+
+lazy_static! {
+	static ref METRICS: Mutex<BTreeMap<String, u64>> = Mutex::new(BTreeMap::new());
+}
+
+/// Increments a named counter by one
+pub fn increment_metric(name: &str) {
+	let mut metrics = lock(&METRICS);
+
+	*metrics.entry(name.to_owned()).or_insert(0) += 1;
+}
+
+/// Returns a snapshot of every counter
+pub fn metrics_snapshot() -> BTreeMap<String, u64> {
+	lock(&METRICS).clone()
+}
@@ -1,9 +1,49 @@
 pub mod api;
+pub mod brownout;
+pub mod compression;
+pub mod context;
+pub mod deprecation;
+pub mod envelope;
+pub mod format;
+pub mod fuzzy;
 pub mod http;
 pub mod lru;
+pub mod mapping;
+pub mod metrics;
+pub mod notice;
+pub mod overlay;
+pub mod package;
+pub mod projection;
+pub mod query;
+pub mod ratelimit;
+pub mod redirect;
+pub mod repositories;
 pub mod runtime;
+pub mod uri;
+pub mod usage;
+pub mod vocabulary;
 
 pub use self::api::*;
+pub use self::brownout::*;
+pub use self::compression::*;
+pub use self::context::*;
+pub use self::deprecation::*;
+pub use self::envelope::*;
+pub use self::format::*;
+pub use self::fuzzy::*;
 pub use self::http::*;
 pub use self::lru::LRU;
+pub use self::mapping::*;
+pub use self::metrics::*;
+pub use self::notice::*;
+pub use self::overlay::*;
+pub use self::package::*;
+pub use self::projection::*;
+pub use self::query::*;
+pub use self::ratelimit::*;
+pub use self::redirect::*;
+pub use self::repositories::*;
 pub use self::runtime::*;
+pub use self::uri::*;
+pub use self::usage::*;
+pub use self::vocabulary::*;
@@ -0,0 +1,119 @@
+use super::{load_json_config, route_deprecation, RequestContext};
+use chrono::{Datelike, Utc};
+use lazy_static::lazy_static;
+use serde::Deserialize;
+use serde_json::{json, Value};
+
+#[derive(Deserialize, Default)]
+pub(super) struct NoticeConfig {
+	#[serde(default)]
+	variants: Vec<NoticeVariant>,
+}
+
+/// Overrides parts of the notice for matching clients or routes
+/// A variant matches when every condition it sets matches, and the first match wins
+#[derive(Deserialize)]
+struct NoticeVariant {
+	user_agent: Option<String>,
+	route: Option<String>,
+	api: Option<String>,
+	data: Option<String>,
+	migration: Option<String>,
+}
+
+impl NoticeVariant {
+	fn matches(&self, context: &RequestContext) -> bool {
+		let user_agent = match &self.user_agent {
+			Some(pattern) => match &context.user_agent {
+				Some(user_agent) => user_agent.to_lowercase().contains(&pattern.to_lowercase()),
+				None => false,
+			},
+			None => true,
+		};
+
+		let route = match &self.route {
+			Some(route) => *route == context.path,
+			None => true,
+		};
+
+		user_agent && route
+	}
+}
+
+lazy_static! {
+	/// Notice variants loaded from the JSON file at 'AVERTER_NOTICE_CONFIG'
+	static ref CONFIG: NoticeConfig = load_json_config("AVERTER_NOTICE_CONFIG", "notice");
+}
+
+/// Returns the notice variants loaded at startup
+pub(super) fn notice_config() -> &'static NoticeConfig {
+	&CONFIG
+}
+
+/// Checks if any notice variant depends on the User-Agent
+pub fn notice_varies_by_user_agent() -> bool {
+	CONFIG
+		.variants
+		.iter()
+		.any(|variant| variant.user_agent.is_some())
+}
+
+/// Renders the notice block for the request being handled
+/// Returns nothing when the client has acknowledged the notice
+pub fn render_notice(context: Option<&RequestContext>) -> Option<Value> {
+	if matches!(context, Some(context) if context.hide_notice) {
+		return None;
+	}
+
+	let variant = context.and_then(|context| {
+		CONFIG
+			.variants
+			.iter()
+			.find(|variant| variant.matches(context))
+	});
+
+	let template = |field: fn(&NoticeVariant) -> &Option<String>, default: &str| {
+		variant
+			.and_then(|variant| field(variant).clone())
+			.unwrap_or_else(|| default.to_owned())
+	};
+
+	let sunset = context
+		.and_then(|context| route_deprecation(&context.path).sunset)
+		.map(|sunset| sunset.format("%B %-d, %Y").to_string());
+
+	let mut migration = template(
+		|variant| &variant.migration,
+		env!("CANISTER_NOTICE_MIGRATION"),
+	);
+
+	// Templates that don't mention the sunset still get told about it
+	if sunset.is_some() && !migration.contains("{{sunset}}") {
+		migration.push_str(" This route will stop working on {{sunset}}.");
+	}
+
+	let render = |template: String| {
+		template
+			.replace("{{docs}}", env!("CANISTER_DOCS_ENDPOINT"))
+			.replace("{{year}}", &Utc::now().year().to_string())
+			.replace(
+				"{{route}}",
+				context
+					.map(|context| context.path.as_str())
+					.unwrap_or_default(),
+			)
+			.replace(
+				"{{v2_url}}",
+				context
+					.and_then(|context| context.v2_url.as_deref())
+					.unwrap_or(env!("CANISTER_DOCS_ENDPOINT")),
+			)
+			.replace("{{sunset}}", sunset.as_deref().unwrap_or("a future date"))
+	};
+
+	Some(json!({
+		"api": render(template(|variant| &variant.api, env!("CANISTER_NOTICE_API"))),
+		"data": render(template(|variant| &variant.data, env!("CANISTER_NOTICE_DATA"))),
+		"migration": render(migration),
+	}))
+}
@@ -0,0 +1,148 @@
+use super::{handle_error, normalize_uri};
+use anyhow::anyhow;
+use lazy_static::lazy_static;
+use serde::Deserialize;
+use serde_json::from_str;
+use std::{
+	env::var,
+	fs,
+	sync::atomic::{AtomicBool, Ordering},
+	time::Duration,
+	time::SystemTime,
+};
+use tokio::{sync::RwLock, time::interval};
+
+const OVERLAY_PATH_ENV: &str = "AVERTER_SAFETY_OVERLAY";
+const OVERLAY_RELOAD_INTERVAL: Duration = Duration::from_secs(30);
+
+/// Whether the overlay file was missing on the last reload, so a missing file is only logged once
+static OVERLAY_MISSING: AtomicBool = AtomicBool::new(false);
+
+#[derive(Deserialize, Default)]
+struct Overlay {
+	#[serde(default)]
+	deny: Vec<OverlayEntry>,
+	#[serde(default)]
+	allow: Vec<OverlayEntry>,
+}
+
+#[derive(Deserialize)]
+struct OverlayEntry {
+	pattern: String,
+	reason: Option<String>,
+}
+
+/// A verdict from the operator-maintained safety overlay
+pub struct OverlayVerdict {
+	pub safe: bool,
+	pub reason: Option<String>,
+}
+
+lazy_static! {
+	static ref OVERLAY: RwLock<(Overlay, Option<SystemTime>)> =
+		RwLock::new((Overlay::default(), None));
+}
+
+/// Checks a URI against the safety overlay
+/// Denylist entries take precedence over allowlist entries
+pub async fn check_overlay(uri: &str) -> Option<OverlayVerdict> {
+	let key = normalize_uri(uri);
+	let overlay = OVERLAY.read().await;
+
+	let verdict = |entry: &OverlayEntry, safe: bool| OverlayVerdict {
+		safe,
+		reason: entry.reason.clone(),
+	};
+
+	if let Some(entry) = overlay
+		.0
+		.deny
+		.iter()
+		.find(|entry| matches(&entry.pattern, &key))
+	{
+		return Some(verdict(entry, false));
+	}
+
+	overlay
+		.0
+		.allow
+		.iter()
+		.find(|entry| matches(&entry.pattern, &key))
+		.map(|entry| verdict(entry, true))
+}
+
+/// Matches a normalised URI against an overlay pattern
+/// Patterns starting with '*.' match any subdomain, others match the URI or its subpaths
+fn matches(pattern: &str, key: &str) -> bool {
+	match pattern.trim().strip_prefix("*.") {
+		Some(suffix) => {
+			let suffix = suffix.to_lowercase();
+			let host = key.split('/').next().unwrap_or(key);
+			host.ends_with(&format!(".{suffix}"))
+		}
+
+		None => {
+			let pattern = normalize_uri(pattern);
+			key == pattern || key.starts_with(&format!("{pattern}/"))
+		}
+	}
+}
+
+/// Periodically reloads the safety overlay from the path in 'AVERTER_SAFETY_OVERLAY'
+/// The file is only re-read when its modification time changes
+pub async fn watch_safety_overlay() {
+	let path = match var(OVERLAY_PATH_ENV) {
+		Ok(path) => path,
+		Err(_) => return,
+	};
+
+	let mut interval = interval(OVERLAY_RELOAD_INTERVAL);
+	loop {
+		interval.tick().await;
+		reload_overlay(&path).await;
+	}
+}
+
+async fn reload_overlay(path: &str) {
+	let modified = match fs::metadata(path).and_then(|metadata| metadata.modified()) {
+		Ok(modified) => {
+			OVERLAY_MISSING.store(false, Ordering::Relaxed);
+			modified
+		}
+		Err(err) => {
+			if !OVERLAY_MISSING.swap(true, Ordering::Relaxed) {
+				println!("overlay -> MISS {path} ({err})");
+			}
+
+			return;
+		}
+	};
+
+	if OVERLAY.read().await.1 == Some(modified) {
+		return;
+	}
+
+	let overlay = match fs::read_to_string(path) {
+		Ok(contents) => match from_str::<Overlay>(&contents) {
+			Ok(overlay) => overlay,
+			Err(err) => {
+				// Keep the previous overlay but skip this revision until the file changes
+				handle_error(&anyhow!("Failed to parse safety overlay at {path} ({err})"));
+				OVERLAY.write().await.1 = Some(modified);
+				return;
+			}
+		},
+		Err(err) => {
+			handle_error(&anyhow!("Failed to read safety overlay at {path} ({err})"));
+			return;
+		}
+	};
+
+	println!(
+		"overlay -> LOAD {} deny, {} allow",
+		overlay.deny.len(),
+		overlay.allow.len()
+	);
+
+	*OVERLAY.write().await = (overlay, Some(modified));
+}
@@ -0,0 +1,213 @@
+use super::{
+	error_respond, map_fields, Field, RepositoryRecord, Request, Response, REPOSITORY_FIELDS,
+};
+use serde::{Deserialize, Serialize};
+use serde_json::{to_value, Value};
+
+#[derive(Serialize, Deserialize, Clone)]
+pub struct PackageRecord {
+	pub package: String,
+	pub architecture: String,
+	pub price: String,
+	#[serde(rename = "isCurrent", default)]
+	pub is_current: bool,
+	pub version: String,
+	pub name: Option<String>,
+	pub description: Option<String>,
+	pub author: Option<String>,
+	pub maintainer: Option<String>,
+	pub depiction: Option<String>,
+	#[serde(rename = "sileoDepiction")]
+	pub sileo_depiction: Option<String>,
+	pub header: Option<String>,
+	#[serde(rename = "tintColor")]
+	pub tint_color: Option<String>,
+	pub icon: Option<String>,
+	pub section: Option<String>,
+	#[serde(default)]
+	pub repository: Option<RepositoryRecord>,
+}
+
+/// Field mapping from a Canister v2 package to a v1 package
+pub static PACKAGE_FIELDS: &[Field] = &[
+	Field::Rename("package", "identifier"),
+	Field::Rename("architecture", "architecture"),
+	Field::Rename("price", "price"),
+	Field::Computed("pricing", price_field),
+	Field::Rename("version", "latestVersion"),
+	Field::Rename("name", "name"),
+	Field::Rename("description", "description"),
+	Field::Rename("author", "author"),
+	Field::Rename("maintainer", "maintainer"),
+	Field::Rename("depiction", "depiction"),
+	Field::Rename("sileoDepiction", "nativeDepiction"),
+	Field::Rename("header", "header"),
+	Field::Rename("tintColor", "tintColor"),
+	Field::Rename("icon", "packageIcon"),
+	Field::Rename("section", "section"),
+	Field::Nested("repository", "repository", REPOSITORY_FIELDS),
+];
+
+/// Reshapes a Canister v2 package into a v1 package
+pub fn package_json(item: &PackageRecord) -> Value {
+	match to_value(item) {
+		Ok(value) => map_fields(&value, PACKAGE_FIELDS),
+		Err(_) => Value::Null,
+	}
+}
+
+/// Computes the structured price of a v2 package
+fn price_field(item: &Value) -> Value {
+	let price = item
+		.get("price")
+		.and_then(Value::as_str)
+		.unwrap_or_default();
+	to_value(parse_price(price)).unwrap_or(Value::Null)
+}
+
+/// Debian architectures used by jailbroken iOS devices
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum Architecture {
+	/// Rootful jailbreaks (iphoneos-arm)
+	Rootful,
+	/// Rootless jailbreaks (iphoneos-arm64)
+	Rootless,
+}
+
+impl Architecture {
+	/// Parses an architecture from its Debian name or a rootful/rootless alias
+	fn parse(value: &str) -> Option<Self> {
+		match value.trim().to_lowercase().as_str() {
+			"iphoneos-arm" | "rootful" => Some(Self::Rootful),
+			"iphoneos-arm64" | "rootless" => Some(Self::Rootless),
+			_ => None,
+		}
+	}
+
+	/// Returns the Debian name of the architecture
+	pub fn as_str(self) -> &'static str {
+		match self {
+			Self::Rootful => "iphoneos-arm",
+			Self::Rootless => "iphoneos-arm64",
+		}
+	}
+
+	/// Checks if a package architecture is installable on this architecture
+	/// Packages built for 'all' (themes, scripts) install on both
+	pub fn matches(self, architecture: &str) -> bool {
+		architecture.eq_ignore_ascii_case(self.as_str()) || architecture.eq_ignore_ascii_case("all")
+	}
+}
+
+/// Resolves the requested architecture from the query parameter or the header
+/// The query parameter takes precedence over the 'X-Architecture' header
+pub fn requested_architecture(
+	req: &Request,
+	query: Option<&str>,
+) -> Result<Option<Architecture>, Response> {
+	let value = match query {
+		Some(value) => Some(value),
+		None => match req.headers().get("X-Architecture") {
+			Some(value) => value.to_str().ok(),
+			None => None,
+		},
+	};
+
+	match value {
+		Some(value) => match Architecture::parse(value) {
+			Some(architecture) => Ok(Some(architecture)),
+			None => Err(error_respond(
+				400,
+				"Invalid architecture, expected \'iphoneos-arm\' or \'iphoneos-arm64\'",
+			)),
+		},
+		None => Ok(None),
+	}
+}
+
+/// Structured price information parsed from a Canister price string
+#[derive(Serialize)]
+pub struct Price {
+	pub free: bool,
+	pub amount: Option<f64>,
+	pub currency: Option<String>,
+	pub original: String,
+}
+
+/// Parses a Canister price string into structured price information
+/// Handles formats like 'Free', '$1.99', '1.99 USD', 'EUR 2,49' and 'Paid'
+pub fn parse_price(price: &str) -> Price {
+	let trimmed = price.trim();
+	if trimmed.is_empty() || trimmed.eq_ignore_ascii_case("free") {
+		return Price {
+			free: true,
+			amount: None,
+			currency: None,
+			original: price.to_owned(),
+		};
+	}
+
+	let amount = parse_amount(trimmed);
+	let currency = match amount {
+		Some(_) => parse_currency(trimmed),
+		None => None,
+	};
+
+	Price {
+		free: matches!(amount, Some(amount) if amount <= 0.0),
+		amount,
+		currency,
+		original: price.to_owned(),
+	}
+}
+
+/// Extracts the numeric amount from a price string
+/// The last separator is treated as the decimal point unless it groups thousands
+fn parse_amount(price: &str) -> Option<f64> {
+	let digits = price
+		.chars()
+		.filter(|char| char.is_ascii_digit() || *char == '.' || *char == ',')
+		.collect::<String>();
+
+	if !digits.chars().any(|char| char.is_ascii_digit()) {
+		return None;
+	}
+
+	let amount = match digits.rfind(['.', ',']) {
+		Some(index) => {
+			let (whole, fraction) = digits.split_at(index);
+			let fraction = &fraction[1..];
+			let whole = whole.replace(['.', ','], "");
+
+			// A single separator followed by three digits is a thousands separator
+			match fraction.len() == 3 && !digits[..index].contains(['.', ',']) {
+				true => format!("{whole}{fraction}"),
+				false => format!("{whole}.{fraction}"),
+			}
+		}
+		None => digits,
+	};
+
+	amount.parse::<f64>().ok()
+}
+
+/// Detects the currency from a symbol or an ISO 4217 code in a price string
+fn parse_currency(price: &str) -> Option<String> {
+	let symbol = price.chars().find_map(|char| match char {
+		'$' => Some("USD"),
+		'€' => Some("EUR"),
+		'£' => Some("GBP"),
+		'¥' => Some("JPY"),
+		'₹' => Some("INR"),
+		_ => None,
+	});
+
+	if let Some(symbol) = symbol {
+		return Some(symbol.to_owned());
+	}
+
+	price
+		.split(|char: char| !char.is_ascii_alphabetic())
+		.find(|word| word.len() == 3 && word.chars().all(|char| char.is_ascii_uppercase()))
+		.map(|word| word.to_owned())
+}
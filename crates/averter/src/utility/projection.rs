@@ -0,0 +1,118 @@
+use super::{error_respond, Field, Request, Response};
+use serde_json::{Map, Value};
+use url::form_urlencoded::parse;
+
+/// A set of v1 fields the client asked for with 'fields='
+/// Paths are dotted, so 'repository.slug' keeps only the slug of the nested repository
+pub struct Projection {
+	paths: Vec<Vec<String>>,
+}
+
+/// Lists every dotted path a v1 object with the given fields can have
+pub fn schema_paths(fields: &[Field]) -> Vec<String> {
+	let mut paths = Vec::new();
+	for field in fields {
+		match field {
+			Field::Rename(_, name) | Field::Computed(name, _) => paths.push(name.to_string()),
+			Field::Nested(_, name, fields) => {
+				paths.push(name.to_string());
+				paths.extend(
+					schema_paths(fields)
+						.into_iter()
+						.map(|path| format!("{name}.{path}")),
+				);
+			}
+		}
+	}
+
+	paths
+}
+
+/// Reads the 'fields' parameter and validates it against the v1 schema
+/// Extra fields that routes add on top of the schema can be passed in 'extra'
+pub fn requested_projection(
+	req: &Request,
+	schema: &[Field],
+	extra: &[&str],
+) -> Result<Option<Projection>, Response> {
+	let fields = parse(req.query_string().as_bytes())
+		.find(|(key, _)| key == "fields")
+		.map(|(_, value)| value.into_owned());
+
+	let fields = match fields {
+		Some(fields) => fields,
+		None => return Ok(None),
+	};
+
+	let known = schema_paths(schema);
+	let mut paths = Vec::new();
+	for field in fields.split(',').map(|field| field.trim()) {
+		if field.is_empty() {
+			continue;
+		}
+
+		if !known.iter().any(|path| path == field) && !extra.contains(&field) {
+			return Err(error_respond(400, &format!("Invalid field: \'{field}\'")));
+		}
+
+		paths.push(field.split('.').map(|part| part.to_owned()).collect());
+	}
+
+	match paths.is_empty() {
+		true => Err(error_respond(
+			400,
+			"Missing fields in query parameter: \'fields\'",
+		)),
+		false => Ok(Some(Projection { paths })),
+	}
+}
+
+/// Keeps only the projected fields of a v1 object or list, in their original order
+/// Values are returned as-is when no projection was requested
+pub fn project(projection: Option<&Projection>, value: Value) -> Value {
+	let projection = match projection {
+		Some(projection) => projection,
+		None => return value,
+	};
+
+	let paths = projection
+		.paths
+		.iter()
+		.map(|path| path.as_slice())
+		.collect::<Vec<&[String]>>();
+
+	project_value(value, &paths)
+}
+
+fn project_value(value: Value, paths: &[&[String]]) -> Value {
+	match value {
+		Value::Object(object) => {
+			let mut projected = Map::new();
+			for (key, value) in object {
+				let children = paths
+					.iter()
+					.filter(|path| path.first() == Some(&key))
+					.map(|path| &path[1..])
+					.collect::<Vec<&[String]>>();
+
+				// Asking for a whole field takes precedence over asking for some of its fields
+				if children.iter().any(|path| path.is_empty()) {
+					projected.insert(key, value);
+				} else if !children.is_empty() {
+					projected.insert(key, project_value(value, &children));
+				}
+			}
+
+			Value::Object(projected)
+		}
+
+		Value::Array(items) => Value::Array(
+			items
+				.into_iter()
+				.map(|item| project_value(item, paths))
+				.collect(),
+		),
+
+		value => value,
+	}
+}
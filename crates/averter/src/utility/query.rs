@@ -0,0 +1,75 @@
+use lazy_static::lazy_static;
+use std::env::var;
+use unicode_normalization::UnicodeNormalization;
+
+/// How a search query is normalised before cache lookup and upstream calls
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum QueryNormalization {
+	/// Trim, collapse whitespace, apply NFKC and fold case
+	Full,
+	/// Everything except case folding, for routes where Canister is case-sensitive
+	PreserveCase,
+	/// Pass the query through untouched
+	Off,
+}
+
+/// Routes whose queries can be normalised
+/// Each route is configured through 'AVERTER_NORMALIZE_<ROUTE>' (full, preserve-case or off)
+#[derive(Clone, Copy)]
+pub enum QueryRoute {
+	PackageSearch,
+	RepositorySearch,
+	RepositoryRanking,
+}
+
+impl QueryRoute {
+	fn env_key(self) -> &'static str {
+		match self {
+			Self::PackageSearch => "AVERTER_NORMALIZE_PACKAGE_SEARCH",
+			Self::RepositorySearch => "AVERTER_NORMALIZE_REPOSITORY_SEARCH",
+			Self::RepositoryRanking => "AVERTER_NORMALIZE_REPOSITORY_RANKING",
+		}
+	}
+}
+
+lazy_static! {
+	static ref PACKAGE_SEARCH: QueryNormalization = load_normalization(QueryRoute::PackageSearch);
+	static ref REPOSITORY_SEARCH: QueryNormalization =
+		load_normalization(QueryRoute::RepositorySearch);
+	static ref REPOSITORY_RANKING: QueryNormalization =
+		load_normalization(QueryRoute::RepositoryRanking);
+}
+
+fn load_normalization(route: QueryRoute) -> QueryNormalization {
+	match var(route.env_key()).as_deref() {
+		Ok("off") => QueryNormalization::Off,
+		Ok("preserve-case") => QueryNormalization::PreserveCase,
+		_ => QueryNormalization::Full,
+	}
+}
+
+/// Normalises a query according to the route's configuration
+/// This keeps equivalent queries like 'Flex' and ' flex ' on the same cache entry
+pub fn normalize_query(route: QueryRoute, query: &str) -> String {
+	let normalization = match route {
+		QueryRoute::PackageSearch => *PACKAGE_SEARCH,
+		QueryRoute::RepositorySearch => *REPOSITORY_SEARCH,
+		QueryRoute::RepositoryRanking => *REPOSITORY_RANKING,
+	};
+
+	if normalization == QueryNormalization::Off {
+		return query.to_owned();
+	}
+
+	let query = query
+		.nfkc()
+		.collect::<String>()
+		.split_whitespace()
+		.collect::<Vec<&str>>()
+		.join(" ");
+
+	match normalization {
+		QueryNormalization::Full => query.to_lowercase(),
+		_ => query,
+	}
+}
@@ -0,0 +1,227 @@
+use super::{env_list, error_respond, increment_metric, is_v1_route, lock, Request, Response};
+use lazy_static::lazy_static;
+use std::{
+	collections::{HashMap, HashSet, VecDeque},
+	env::var,
+	net::{IpAddr, SocketAddr},
+	sync::Mutex,
+	time::Instant,
+};
+use url::form_urlencoded::parse;
+
+/// Upper bound on tracked clients before the oldest buckets are dropped
+const MAX_BUCKETS: usize = 100_000;
+
+/// Routes that cost more than one token, as (path, parameter that must be present, cost)
+/// Multi-lookups and rankings fan out to far more upstream work than a single lookup
+const ROUTE_COSTS: &[(&str, Option<&str>, u32)] = &[
+	("/community/packages/lookup", None, 5),
+	("/community/repositories/search", Some("ranking"), 5),
+	("/community/repositories/safety", Some("queries"), 3),
+];
+
+struct Bucket {
+	tokens: f64,
+	updated: Instant,
+}
+
+/// Token buckets bounded to 'MAX_BUCKETS' clients
+/// Buckets are evicted in the order they were created, so eviction never scans the map
+#[derive(Default)]
+struct Buckets {
+	entries: HashMap<String, Bucket>,
+	order: VecDeque<String>,
+}
+
+impl Buckets {
+	/// Returns the bucket for a client, creating a full one if it isn't tracked yet
+	fn entry(&mut self, key: String, capacity: f64, now: Instant) -> &mut Bucket {
+		if !self.entries.contains_key(&key) {
+			while self.entries.len() >= MAX_BUCKETS {
+				match self.order.pop_front() {
+					Some(oldest) => self.entries.remove(&oldest),
+					None => break,
+				};
+			}
+
+			self.order.push_back(key.clone());
+		}
+
+		self.entries.entry(key).or_insert(Bucket {
+			tokens: capacity,
+			updated: now,
+		})
+	}
+}
+
+/// The outcome of charging a request against its client's bucket
+pub struct RateLimit {
+	pub allowed: bool,
+	pub limit: u32,
+	pub remaining: u32,
+	/// Seconds until the bucket is full again
+	pub reset: u64,
+	/// Seconds until the request could be retried, only set when it was refused
+	pub retry_after: Option<u64>,
+}
+
+lazy_static! {
+	/// Bucket size from 'AVERTER_RATE_LIMIT_CAPACITY', where '0' disables rate limiting
+	static ref CAPACITY: u32 = env_number("AVERTER_RATE_LIMIT_CAPACITY", 120.0) as u32;
+
+	/// Tokens added per second from 'AVERTER_RATE_LIMIT_REFILL'
+	static ref REFILL: f64 = env_number("AVERTER_RATE_LIMIT_REFILL", 2.0).max(0.01);
+
+	/// API keys that get their own bucket, from 'AVERTER_RATE_LIMIT_API_KEYS'
+	static ref API_KEYS: HashSet<String> = env_list("AVERTER_RATE_LIMIT_API_KEYS")
+		.into_iter()
+		.collect();
+
+	/// Proxies in front of averter that append to 'X-Forwarded-For', from 'AVERTER_TRUSTED_PROXIES'
+	static ref TRUSTED_PROXIES: usize = env_number("AVERTER_TRUSTED_PROXIES", 0.0) as usize;
+
+	static ref BUCKETS: Mutex<Buckets> = Mutex::new(Buckets::default());
+}
+
+fn env_number(key: &str, default: f64) -> f64 {
+	match var(key) {
+		Ok(value) => value.parse().unwrap_or(default),
+		Err(_) => default,
+	}
+}
+
+/// Returns the client IP, only trusting the 'X-Forwarded-For' entries added by configured proxies
+/// Each trusted proxy appends the address it saw, so the right-most untrusted entry is the client
+fn client_ip(req: &Request) -> Option<IpAddr> {
+	if *TRUSTED_PROXIES > 0 {
+		let forwarded = req
+			.headers()
+			.get_all("X-Forwarded-For")
+			.filter_map(|value| value.to_str().ok())
+			.flat_map(|value| value.split(','))
+			.map(|entry| entry.trim())
+			.collect::<Vec<&str>>();
+
+		if forwarded.len() >= *TRUSTED_PROXIES {
+			let entry = forwarded[forwarded.len() - *TRUSTED_PROXIES];
+			// Entries may carry a port, which would give every connection its own bucket
+			let ip = match entry.parse::<IpAddr>() {
+				Ok(ip) => Some(ip),
+				Err(_) => entry.parse::<SocketAddr>().ok().map(|address| address.ip()),
+			};
+
+			if ip.is_some() {
+				return ip;
+			}
+		}
+	}
+
+	req.peer_addr().map(|address| address.ip())
+}
+
+/// Identifies the client a request is charged to
+/// Configured API keys take precedence, then the real client IP, then the User-Agent
+/// Unknown keys are ignored so clients can't mint fresh buckets by sending random keys
+fn client_key(req: &Request) -> String {
+	let header = |name: &str| {
+		req.headers()
+			.get(name)
+			.and_then(|value| value.to_str().ok())
+			.filter(|value| !value.is_empty())
+			.map(|value| value.to_owned())
+	};
+
+	if let Some(key) = header("X-API-Key").filter(|key| API_KEYS.contains(key)) {
+		return format!("key:{key}");
+	}
+
+	if let Some(ip) = client_ip(req) {
+		return format!("ip:{ip}");
+	}
+
+	format!("ua:{}", header("User-Agent").unwrap_or_default())
+}
+
+/// Returns how many tokens a request costs
+fn route_cost(req: &Request) -> u32 {
+	let path = req.path().trim_end_matches('/');
+	let has_param = |name: &str| parse(req.query_string().as_bytes()).any(|(key, _)| key == name);
+
+	ROUTE_COSTS
+		.iter()
+		.find(|(route, param, _)| {
+			*route == path
+				&& match param {
+					Some(param) => has_param(param),
+					None => true,
+				}
+		})
+		.map_or(1, |(_, _, cost)| *cost)
+}
+
+/// Charges a request against its client's token bucket
+/// Only v1 and v2 passthrough routes are limited, and nothing is returned when limiting is disabled
+pub fn check_rate_limit(req: &Request) -> Option<RateLimit> {
+	let path = req.path();
+	if *CAPACITY == 0 || !(is_v1_route(path) || path.starts_with("/v2/")) {
+		return None;
+	}
+
+	let key = client_key(req);
+	let cost = f64::from(route_cost(req));
+	let capacity = f64::from(*CAPACITY);
+	let now = Instant::now();
+
+	let mut buckets = lock(&BUCKETS);
+
+	let bucket = buckets.entry(key, capacity, now);
+
+	let elapsed = now.duration_since(bucket.updated).as_secs_f64();
+	bucket.tokens = (bucket.tokens + elapsed * *REFILL).min(capacity);
+	bucket.updated = now;
+
+	let allowed = bucket.tokens >= cost;
+	if allowed {
+		bucket.tokens -= cost;
+	} else {
+		increment_metric("rate_limited");
+	}
+
+	Some(RateLimit {
+		allowed,
+		limit: *CAPACITY,
+		remaining: bucket.tokens.floor() as u32,
+		reset: ((capacity - bucket.tokens) / *REFILL).ceil() as u64,
+		retry_after: match allowed {
+			true => None,
+			false => Some(((cost - bucket.tokens) / *REFILL).ceil() as u64),
+		},
+	})
+}
+
+/// Builds the 'RateLimit-*' and 'Retry-After' headers for a rate limit
+/// Header names are lowercase so they can be used as static header names
+pub fn rate_limit_headers(limit: &RateLimit) -> Vec<(&'static str, String)> {
+	let mut headers = vec![
+		("ratelimit-limit", limit.limit.to_string()),
+		("ratelimit-remaining", limit.remaining.to_string()),
+		("ratelimit-reset", limit.reset.to_string()),
+	];
+
+	if let Some(retry_after) = limit.retry_after {
+		headers.push(("retry-after", retry_after.to_string()));
+	}
+
+	headers
+}
+
+/// Returns a v1 error response for a request that was rate limited
+pub fn rate_limited_response(limit: &RateLimit) -> Response {
+	error_respond(
+		429,
+		&format!(
+			"Too many requests, please retry in {} seconds",
+			limit.retry_after.unwrap_or(1)
+		),
+	)
+}
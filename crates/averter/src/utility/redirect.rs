@@ -0,0 +1,255 @@
+use super::{increment_metric, loaded_repository_dataset, Param, Request};
+use crate::routes::mappings::MAPPINGS;
+use lazy_static::lazy_static;
+use std::env::var;
+use url::{form_urlencoded::parse, Url};
+
+/// How a v1 route translates into a v2 URL
+/// Placeholders like '{id}' in the endpoint are filled from the v1 parameter of the same name
+#[derive(Clone, Copy)]
+struct RedirectRoute {
+	path: &'static str,
+	when: &'static str,
+	endpoint: &'static str,
+	params: &'static [Param],
+	/// Whether the '{id}' placeholder is a v1 repository slug, alias or URI that must become a v2 slug
+	repository: bool,
+}
+
+/// v1 routes with a hand-written handler and a v2 equivalent
+/// Mapped routes are translated from 'MAPPINGS', and the first route whose 'when' parameter is present is used
+const REDIRECT_ROUTES: &[RedirectRoute] = &[
+	RedirectRoute {
+		path: "/community/packages",
+		when: "id",
+		endpoint: "/jailbreak/package/{id}",
+		params: &[],
+		repository: false,
+	},
+	RedirectRoute {
+		path: "/community/packages/search",
+		when: "query",
+		endpoint: "/jailbreak/package/search",
+		params: &[Param::Query("query", "q")],
+		repository: false,
+	},
+	RedirectRoute {
+		path: "/community/repositories",
+		when: "id",
+		endpoint: "/jailbreak/repository/{id}",
+		params: &[],
+		repository: true,
+	},
+	RedirectRoute {
+		path: "/community/repositories/packages",
+		when: "id",
+		endpoint: "/jailbreak/repository/{id}/packages",
+		params: &[Param::Query("page", "page"), Param::Query("limit", "limit")],
+		repository: true,
+	},
+	RedirectRoute {
+		path: "/community/repositories/search",
+		when: "query",
+		endpoint: "/jailbreak/repository/search",
+		params: &[Param::Query("query", "q")],
+		repository: false,
+	},
+	RedirectRoute {
+		path: "/community/repositories/search",
+		when: "ranking",
+		endpoint: "/jailbreak/repository/ranking",
+		params: &[Param::Query("ranking", "rank")],
+		repository: false,
+	},
+	RedirectRoute {
+		path: "/community/repositories/safety",
+		when: "queries",
+		endpoint: "/jailbreak/repository/safety",
+		params: &[Param::Query("queries", "uris")],
+		repository: false,
+	},
+	RedirectRoute {
+		path: "/community/repositories/safety",
+		when: "query",
+		endpoint: "/jailbreak/repository/safety",
+		params: &[Param::Query("query", "uris")],
+		repository: false,
+	},
+];
+
+/// A v2 URL and whether it carries every parameter of the v1 request
+struct Translation {
+	url: String,
+	is_exact: bool,
+}
+
+lazy_static! {
+	/// v1 paths that may be redirected, from 'AVERTER_REDIRECT_ROUTES' ('*' for all)
+	static ref ENABLED_ROUTES: Vec<String> = env_list("AVERTER_REDIRECT_ROUTES");
+
+	/// User-Agent substrings of clients that are redirected, from 'AVERTER_REDIRECT_USER_AGENTS'
+	static ref USER_AGENTS: Vec<String> = env_list("AVERTER_REDIRECT_USER_AGENTS")
+		.into_iter()
+		.map(|agent| agent.to_lowercase())
+		.collect();
+}
+
+/// Reads a comma-separated list from an environment variable
+pub(super) fn env_list(key: &str) -> Vec<String> {
+	match var(key) {
+		Ok(value) => value
+			.split(',')
+			.map(|item| item.trim().to_owned())
+			.filter(|item| !item.is_empty())
+			.collect(),
+		Err(_) => Vec::new(),
+	}
+}
+
+/// Checks if the client should be redirected instead of proxied
+/// Clients opt in with the 'X-Averter-Redirect: v2' header or by matching a User-Agent pattern
+fn wants_redirect(req: &Request) -> bool {
+	let header = req
+		.headers()
+		.get("X-Averter-Redirect")
+		.and_then(|value| value.to_str().ok());
+
+	if matches!(header, Some(value) if value.eq_ignore_ascii_case("v2")) {
+		return true;
+	}
+
+	let user_agent = match req.headers().get("User-Agent") {
+		Some(user_agent) => user_agent.to_str().unwrap_or_default().to_lowercase(),
+		None => return false,
+	};
+
+	USER_AGENTS
+		.iter()
+		.any(|agent| user_agent.contains(agent.as_str()))
+}
+
+/// Returns the v2 URL a request should be redirected to, if redirect mode applies
+/// Requests with parameters that have no v2 equivalent are proxied instead of redirected
+pub fn redirect_location(req: &Request) -> Option<String> {
+	let path = req.path().trim_end_matches('/');
+	let enabled = ENABLED_ROUTES
+		.iter()
+		.any(|route| route == "*" || route == path);
+
+	if !enabled || !wants_redirect(req) {
+		return None;
+	}
+
+	let translation = translate(req)?;
+	if !translation.is_exact {
+		return None;
+	}
+
+	increment_metric("redirects");
+	Some(translation.url)
+}
+
+/// Translates a v1 request into the equivalent v2 URL, if there is one
+pub fn v2_url(req: &Request) -> Option<String> {
+	translate(req).map(|translation| translation.url)
+}
+
+/// Finds the route a v1 request translates with, preferring the route mappings
+fn find_route(path: &str, has_param: impl Fn(&str) -> bool) -> Option<RedirectRoute> {
+	let mapped = MAPPINGS.iter().find_map(|mapping| {
+		let when = *mapping.required.first()?;
+		match mapping.path == path && has_param(when) {
+			true => Some(RedirectRoute {
+				path: mapping.path,
+				when,
+				endpoint: mapping.endpoint,
+				params: mapping.params,
+				repository: false,
+			}),
+			false => None,
+		}
+	});
+
+	mapped.or_else(|| {
+		REDIRECT_ROUTES
+			.iter()
+			.find(|route| route.path == path && has_param(route.when))
+			.copied()
+	})
+}
+
+/// Translates a v1 request into a v2 URL, noting whether any v1 parameters were dropped
+fn translate(req: &Request) -> Option<Translation> {
+	let path = req.path().trim_end_matches('/');
+	let query = parse(req.query_string().as_bytes())
+		.into_owned()
+		.collect::<Vec<(String, String)>>();
+
+	let value = |name: &str| {
+		query
+			.iter()
+			.find(|(key, _)| key == name)
+			.map(|(_, value)| value.as_str())
+	};
+
+	let route = find_route(path, |name| value(name).is_some())?;
+	let mut used = vec![route.when];
+
+	let mut url = Url::parse(env!("CANISTER_API_ENDPOINT")).ok()?;
+	{
+		let mut segments = url.path_segments_mut().ok()?;
+		segments.pop_if_empty().push("v2");
+
+		for segment in route
+			.endpoint
+			.split('/')
+			.filter(|segment| !segment.is_empty())
+		{
+			let placeholder = segment
+				.strip_prefix('{')
+				.and_then(|segment| segment.strip_suffix('}'));
+
+			let name = match placeholder {
+				Some(name) => name,
+				None => {
+					segments.push(segment);
+					continue;
+				}
+			};
+
+			used.push(name);
+			match route.repository {
+				// v2 only accepts slugs, so aliases and URIs are resolved through the dataset
+				true => {
+					let dataset = loaded_repository_dataset()?;
+					segments.push(&dataset.resolve(value(name)?)?.slug);
+				}
+				false => {
+					segments.push(value(name)?);
+				}
+			};
+		}
+	}
+
+	let mut params = Vec::new();
+	for param in route.params {
+		if let Param::Query(from, to) = param {
+			used.push(from);
+			if let Some(value) = value(from) {
+				params.push((*to, value));
+			}
+		}
+	}
+
+	match params.is_empty() {
+		true => url.set_query(None),
+		false => {
+			url.query_pairs_mut().clear().extend_pairs(params);
+		}
+	}
+
+	Some(Translation {
+		url: url.to_string(),
+		is_exact: query.iter().all(|(key, _)| used.contains(&key.as_str())),
+	})
+}
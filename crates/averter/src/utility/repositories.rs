@@ -0,0 +1,237 @@
+use super::{fetch_v2_uncached, fuzzy_score, map_fields, normalize_uri, Field, Response};
+use lazy_static::lazy_static;
+use serde::{Deserialize, Serialize};
+use serde_json::{to_value, Value};
+use std::{collections::BTreeMap, sync::Arc, time::Duration};
+use tokio::{
+	sync::{Mutex, RwLock},
+	time::interval,
+};
+
+const DATASET_REFRESH_INTERVAL: Duration = Duration::from_secs(600);
+const FUZZY_THRESHOLD: f64 = 0.35;
+
+#[derive(Serialize, Deserialize, Clone)]
+pub struct RepositoryRecord {
+	pub slug: String,
+	pub suite: String,
+	pub uri: String,
+	pub tier: u8,
+	pub aliases: Vec<String>,
+	pub name: Option<String>,
+	pub version: Option<String>,
+	pub component: Option<String>,
+}
+
+/// Field mapping from a Canister v2 repository to a v1 repository
+pub static REPOSITORY_FIELDS: &[Field] = &[
+	Field::Rename("slug", "slug"),
+	Field::Rename("aliases", "aliases"),
+	Field::Rename("uri", "uri"),
+	Field::Rename("version", "version"),
+	Field::Rename("suite", "suite"),
+	Field::Rename("component", "component"),
+	Field::Rename("tier", "ranking"),
+	Field::Rename("name", "name"),
+];
+
+/// How a query identifies a repository, ordered by precedence
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum RepositoryMatch {
+	Slug,
+	Alias,
+	Uri,
+}
+
+impl RepositoryRecord {
+	/// Checks if a query is the repository's slug, one of its aliases or its URI
+	/// URIs are normalised before being compared
+	pub fn matches(&self, query: &str) -> Option<RepositoryMatch> {
+		let query = query.trim();
+		if self.slug.eq_ignore_ascii_case(query) {
+			return Some(RepositoryMatch::Slug);
+		}
+
+		if self
+			.aliases
+			.iter()
+			.any(|alias| alias.eq_ignore_ascii_case(query))
+		{
+			return Some(RepositoryMatch::Alias);
+		}
+
+		match normalize_uri(&self.uri) == normalize_uri(query) {
+			true => Some(RepositoryMatch::Uri),
+			false => None,
+		}
+	}
+}
+
+/// Reshapes a Canister v2 repository into a v1 repository
+pub fn repository_json(item: &RepositoryRecord) -> Value {
+	match to_value(item) {
+		Ok(value) => map_fields(&value, REPOSITORY_FIELDS),
+		Err(_) => Value::Null,
+	}
+}
+
+#[derive(Serialize)]
+struct CanisterQuery {
+	rank: String,
+}
+
+#[derive(Deserialize)]
+struct CanisterResponse {
+	data: Vec<RepositoryRecord>,
+}
+
+/// Every repository known to Canister, indexed by tier
+pub struct RepositoryDataset {
+	pub repositories: Vec<RepositoryRecord>,
+	pub tiers: BTreeMap<u8, Vec<usize>>,
+}
+
+impl RepositoryDataset {
+	fn new(repositories: Vec<RepositoryRecord>) -> Self {
+		let mut tiers: BTreeMap<u8, Vec<usize>> = BTreeMap::new();
+		for (index, repository) in repositories.iter().enumerate() {
+			tiers.entry(repository.tier).or_default().push(index);
+		}
+
+		Self {
+			repositories,
+			tiers,
+		}
+	}
+
+	/// Resolves a repository by its slug, one of its aliases or its URI
+	/// Slugs take precedence over aliases, which take precedence over URIs
+	pub fn resolve(&self, query: &str) -> Option<&RepositoryRecord> {
+		self.repositories
+			.iter()
+			.filter_map(|repository| Some((repository.matches(query)?, repository)))
+			.min_by_key(|(kind, _)| *kind)
+			.map(|(_, repository)| repository)
+	}
+
+	/// Searches the dataset locally by name, slug, aliases and URI
+	/// Used as a fallback when Canister search is unavailable
+	pub fn fuzzy_search(&self, query: &str, limit: usize) -> Vec<&RepositoryRecord> {
+		let uri = normalize_uri(query);
+		let mut matches = self
+			.repositories
+			.iter()
+			.filter_map(|repository| {
+				let score = repository
+					.aliases
+					.iter()
+					.map(|alias| fuzzy_score(query, alias))
+					.chain([
+						fuzzy_score(query, &repository.slug),
+						fuzzy_score(query, repository.name.as_deref().unwrap_or_default()),
+						fuzzy_score(&uri, &normalize_uri(&repository.uri)),
+					])
+					.fold(0.0, f64::max);
+
+				match score >= FUZZY_THRESHOLD {
+					true => Some((score, repository)),
+					false => None,
+				}
+			})
+			.collect::<Vec<(f64, &RepositoryRecord)>>();
+
+		matches.sort_by(|(left_score, left), (right_score, right)| {
+			right_score
+				.total_cmp(left_score)
+				.then_with(|| left.tier.cmp(&right.tier))
+				.then_with(|| left.slug.cmp(&right.slug))
+		});
+
+		matches
+			.into_iter()
+			.take(limit)
+			.map(|(_, repository)| repository)
+			.collect()
+	}
+
+	/// Returns every repository in the given tier
+	pub fn tier(&self, tier: u8) -> impl Iterator<Item = &RepositoryRecord> {
+		self.tiers
+			.get(&tier)
+			.into_iter()
+			.flatten()
+			.map(|index| &self.repositories[*index])
+	}
+}
+
+lazy_static! {
+	static ref DATASET: RwLock<Option<Arc<RepositoryDataset>>> = RwLock::new(None);
+
+	/// Held while the dataset is loaded on first use, so concurrent requests share one fetch
+	static ref FIRST_LOAD: Mutex<()> = Mutex::new(());
+}
+
+/// Returns the in-memory repository dataset
+/// The dataset is fetched on first use if the refresh task hasn't populated it yet
+pub async fn repository_dataset() -> Result<Arc<RepositoryDataset>, Response> {
+	if let Some(dataset) = DATASET.read().await.as_ref() {
+		return Ok(dataset.clone());
+	}
+
+	let _guard = FIRST_LOAD.lock().await;
+	// Another request may have loaded the dataset while this one was waiting
+	if let Some(dataset) = DATASET.read().await.as_ref() {
+		return Ok(dataset.clone());
+	}
+
+	refresh_repository_dataset().await
+}
+
+/// Returns the repository dataset if it has already been loaded, without waiting on it
+/// Used by synchronous callers like the redirect middleware
+pub fn loaded_repository_dataset() -> Option<Arc<RepositoryDataset>> {
+	match DATASET.try_read() {
+		Ok(dataset) => dataset.clone(),
+		Err(_) => None,
+	}
+}
+
+/// Fetches the full ranking list from Canister and replaces the dataset
+async fn refresh_repository_dataset() -> Result<Arc<RepositoryDataset>, Response> {
+	let query = CanisterQuery {
+		rank: "*".to_owned(),
+	};
+
+	let response = match fetch_v2_uncached::<CanisterQuery, CanisterResponse>(
+		query,
+		"/jailbreak/repository/ranking",
+	)
+	.await
+	{
+		Ok(response) => response,
+		Err(err) => return Err(err),
+	};
+
+	let dataset = Arc::new(RepositoryDataset::new(response.data));
+	if cfg!(debug_assertions) {
+		println!(
+			"dataset -> LOAD {} repositories",
+			dataset.repositories.len()
+		);
+	}
+
+	*DATASET.write().await = Some(dataset.clone());
+	Ok(dataset)
+}
+
+/// Periodically refreshes the repository dataset
+/// Failed refreshes keep serving the previous dataset
+pub async fn watch_repository_dataset() {
+	let mut interval = interval(DATASET_REFRESH_INTERVAL);
+	loop {
+		interval.tick().await;
+		if refresh_repository_dataset().await.is_err() {
+			println!("dataset -> FAIL keeping previous dataset");
+		}
+	}
+}
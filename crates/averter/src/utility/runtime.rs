@@ -1,6 +1,14 @@
+use super::{deprecation_config, notice_config};
 use anyhow::Error;
 use once_cell::sync::OnceCell;
 use sentry::integrations::anyhow::capture_anyhow;
+use serde::de::DeserializeOwned;
+use serde_json::from_str;
+use std::{
+	env::var,
+	fs::read_to_string,
+	sync::{Mutex, MutexGuard},
+};
 use surf::{Client, Config, Url};
 
 static CANISTER: OnceCell<Client> = OnceCell::new();
@@ -53,6 +61,36 @@ pub fn canister() -> &'static Client {
 	}
 }
 
+/// Reads a JSON config from the file named by an environment variable
+/// Falls back to the default config when the variable isn't set, and panics on a bad file
+pub fn load_json_config<T: DeserializeOwned + Default>(env_key: &str, kind: &str) -> T {
+	let path = match var(env_key) {
+		Ok(path) => path,
+		Err(_) => return T::default(),
+	};
+
+	match read_to_string(&path).map(|contents| from_str::<T>(&contents)) {
+		Ok(Ok(config)) => config,
+		Ok(Err(err)) => panic!("Failed to parse {kind} config at {path} ({err})"),
+		Err(err) => panic!("Failed to read {kind} config at {path} ({err})"),
+	}
+}
+
+/// Loads every JSON config before the server starts
+/// A bad file fails fast instead of panicking on the first request that needs it
+pub fn load_configs() {
+	deprecation_config();
+	notice_config();
+}
+
+/// Locks a mutex, recovering the data if a thread panicked while holding it
+pub fn lock<T>(mutex: &Mutex<T>) -> MutexGuard<'_, T> {
+	match mutex.lock() {
+		Ok(guard) => guard,
+		Err(poisoned) => poisoned.into_inner(),
+	}
+}
+
 /// Takes an error and reports it to Sentry
 pub fn handle_error(err: &Error) {
 	let uuid = capture_anyhow(err);
@@ -0,0 +1,28 @@
+use url::Url;
+
+/// Normalises a repository URI into a comparable key
+/// The scheme, 'www.' prefix and trailing slashes are dropped and the host is lowercased
+pub fn normalize_uri(uri: &str) -> String {
+	let uri = uri.trim();
+	let parsed = match uri.contains("://") {
+		true => Url::parse(uri),
+		false => Url::parse(&format!("https://{uri}")),
+	};
+
+	let parsed = match parsed {
+		Ok(parsed) => parsed,
+		Err(_) => return uri.trim_end_matches('/').to_lowercase(),
+	};
+
+	let host = match parsed.host_str() {
+		Some(host) => host.trim_start_matches("www."),
+		None => return uri.trim_end_matches('/').to_lowercase(),
+	};
+
+	let host = match parsed.port() {
+		Some(port) => format!("{host}:{port}"),
+		None => host.to_owned(),
+	};
+
+	format!("{host}{}", parsed.path().trim_end_matches('/'))
+}
@@ -0,0 +1,232 @@
+use super::{handle_error, lock};
+use anyhow::anyhow;
+use chrono::{Duration, Utc};
+use lazy_static::lazy_static;
+use serde::{Deserialize, Serialize};
+use serde_json::{from_str, json, to_string, Value};
+use std::{collections::BTreeMap, env::var, fs, sync::Mutex, time::Duration as StdDuration};
+use tokio::time::interval;
+
+const USAGE_PATH_ENV: &str = "AVERTER_USAGE_FILE";
+const USAGE_FLUSH_INTERVAL: StdDuration = StdDuration::from_secs(300);
+
+/// Upper bound on distinct usage rows kept in memory
+const MAX_USAGE_ENTRIES: usize = 10_000;
+
+/// Days of usage kept before rows are dropped
+const USAGE_RETENTION_DAYS: i64 = 90;
+
+/// Known clients as (User-Agent substring, family, kind)
+/// Order matters since browsers and some clients share substrings
+const CLIENTS: &[(&str, &str, &str)] = &[
+	("sileo", "Sileo", "client"),
+	("zebra", "Zebra", "client"),
+	("installer", "Installer", "client"),
+	("cydia", "Cydia", "client"),
+	("saily", "Saily", "client"),
+	("curl", "curl", "script"),
+	("wget", "Wget", "script"),
+	("python", "Python", "script"),
+	("go-http-client", "Go", "script"),
+	("node-fetch", "Node", "script"),
+	("axios", "Node", "script"),
+	("okhttp", "OkHttp", "script"),
+	("mozilla", "Browser", "browser"),
+];
+
+/// A single aggregated usage row
+/// Only the route and normalised client are kept, never the raw User-Agent or IP
+#[derive(Serialize, Deserialize, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub struct UsageKey {
+	pub day: String,
+	pub route: String,
+	pub client: String,
+	pub version: String,
+	pub kind: String,
+}
+
+#[derive(Serialize, Deserialize)]
+struct UsageRow {
+	#[serde(flatten)]
+	key: UsageKey,
+	requests: u64,
+}
+
+lazy_static! {
+	static ref USAGE: Mutex<BTreeMap<UsageKey, u64>> = Mutex::new(BTreeMap::new());
+}
+
+/// Normalises a User-Agent into a client family, major.minor version and kind
+pub fn client_family(user_agent: Option<&str>) -> (&'static str, String, &'static str) {
+	let user_agent = match user_agent {
+		Some(user_agent) if !user_agent.trim().is_empty() => user_agent.to_lowercase(),
+		_ => return ("Unknown", String::new(), "unknown"),
+	};
+
+	let (index, pattern, family, kind) = match CLIENTS.iter().find_map(|(pattern, family, kind)| {
+		user_agent
+			.find(pattern)
+			.map(|index| (index, pattern, family, kind))
+	}) {
+		Some(client) => client,
+		None => return ("Other", String::new(), "other"),
+	};
+
+	// Versions follow the name after a short separator like '/', ' ' or ' - '
+	let rest = &user_agent[index + pattern.len()..];
+	let start = match rest.find(|c: char| c.is_ascii_digit()) {
+		Some(start) if start <= 3 => start,
+		_ => return (family, String::new(), kind),
+	};
+
+	let version = rest[start..]
+		.split(|c: char| !c.is_ascii_digit() && c != '.')
+		.next()
+		.unwrap_or_default()
+		.split('.')
+		.filter(|part| !part.is_empty())
+		.take(2)
+		.collect::<Vec<&str>>()
+		.join(".");
+
+	(family, version, kind)
+}
+
+/// Records a v1 request against its route and client
+pub fn record_usage(route: &str, user_agent: Option<&str>) {
+	let (client, version, kind) = client_family(user_agent);
+	let mut key = UsageKey {
+		day: Utc::now().format("%Y-%m-%d").to_string(),
+		route: route.to_owned(),
+		client: client.to_owned(),
+		version,
+		kind: kind.to_owned(),
+	};
+
+	let mut usage = lock(&USAGE);
+
+	if !usage.contains_key(&key) && usage.len() >= MAX_USAGE_ENTRIES {
+		prune_usage(&mut usage);
+
+		// Rows past the bound are folded into a single row per day and route
+		if usage.len() >= MAX_USAGE_ENTRIES {
+			key.client = "Overflow".to_owned();
+			key.version = String::new();
+			key.kind = "other".to_owned();
+		}
+	}
+
+	*usage.entry(key).or_insert(0) += 1;
+}
+
+/// Drops rows older than the retention period
+fn prune_usage(usage: &mut BTreeMap<UsageKey, u64>) {
+	let cutoff = (Utc::now() - Duration::days(USAGE_RETENTION_DAYS))
+		.format("%Y-%m-%d")
+		.to_string();
+
+	usage.retain(|key, _| key.day >= cutoff);
+}
+
+fn usage_rows() -> Vec<UsageRow> {
+	let usage = lock(&USAGE);
+
+	usage
+		.iter()
+		.map(|(key, requests)| UsageRow {
+			key: key.clone(),
+			requests: *requests,
+		})
+		.collect()
+}
+
+/// Returns every usage row along with totals per route and client
+pub fn usage_report() -> Value {
+	let rows = usage_rows();
+	let mut routes: BTreeMap<&str, u64> = BTreeMap::new();
+	let mut clients: BTreeMap<String, u64> = BTreeMap::new();
+
+	for row in &rows {
+		*routes.entry(&row.key.route).or_insert(0) += row.requests;
+		let client = match row.key.version.is_empty() {
+			true => row.key.client.clone(),
+			false => format!("{} {}", row.key.client, row.key.version),
+		};
+
+		*clients.entry(client).or_insert(0) += row.requests;
+	}
+
+	json!({
+		"totals": {
+			"routes": routes,
+			"clients": clients,
+		},
+		"rows": rows,
+	})
+}
+
+/// Loads previously flushed usage and periodically flushes it to 'AVERTER_USAGE_FILE'
+pub async fn watch_usage() {
+	let path = match var(USAGE_PATH_ENV) {
+		Ok(path) => path,
+		Err(_) => return,
+	};
+
+	load_usage(&path);
+	let mut interval = interval(USAGE_FLUSH_INTERVAL);
+	interval.tick().await;
+
+	loop {
+		interval.tick().await;
+		flush_usage(&path);
+	}
+}
+
+fn load_usage(path: &str) {
+	let contents = match fs::read_to_string(path) {
+		Ok(contents) => contents,
+		Err(err) => {
+			println!("usage -> MISS {path} ({err})");
+			return;
+		}
+	};
+
+	let rows = match from_str::<Vec<UsageRow>>(&contents) {
+		Ok(rows) => rows,
+		Err(err) => {
+			handle_error(&anyhow!("Failed to parse usage at {path} ({err})"));
+			return;
+		}
+	};
+
+	let mut usage = lock(&USAGE);
+
+	for row in rows.into_iter().take(MAX_USAGE_ENTRIES) {
+		*usage.entry(row.key).or_insert(0) += row.requests;
+	}
+
+	prune_usage(&mut usage);
+	println!("usage -> LOAD {} rows", usage.len());
+}
+
+fn flush_usage(path: &str) {
+	{
+		let mut usage = lock(&USAGE);
+
+		prune_usage(&mut usage);
+	}
+
+	let contents = match to_string(&usage_rows()) {
+		Ok(contents) => contents,
+		Err(err) => {
+			handle_error(&anyhow!("Failed to serialize usage ({err})"));
+			return;
+		}
+	};
+
+	// Writing to a temporary file first keeps the previous flush intact on failure
+	let temporary = format!("{path}.tmp");
+	if let Err(err) = fs::write(&temporary, contents).and_then(|_| fs::rename(&temporary, path)) {
+		handle_error(&anyhow!("Failed to write usage to {path} ({err})"));
+	}
+}
@@ -0,0 +1,118 @@
+use super::{bigram_similarity, edit_distance, lock, PackageRecord};
+use lazy_static::lazy_static;
+use std::{
+	collections::{HashSet, VecDeque},
+	sync::Mutex,
+};
+
+const VOCABULARY_MAX_SIZE: usize = 20_000;
+const SUGGESTION_MIN_SIMILARITY: f64 = 0.5;
+
+/// Package identifiers and names seen in Canister responses
+/// The oldest terms are evicted once the vocabulary is full
+struct Vocabulary {
+	terms: HashSet<String>,
+	queue: VecDeque<String>,
+}
+
+lazy_static! {
+	static ref VOCABULARY: Mutex<Vocabulary> = Mutex::new(Vocabulary {
+		terms: HashSet::new(),
+		queue: VecDeque::new(),
+	});
+}
+
+/// Records the identifiers and names of packages for search suggestions
+pub fn record_packages(packages: &[PackageRecord]) {
+	record_terms(packages.iter().flat_map(|package| {
+		[Some(package.package.as_str()), package.name.as_deref()]
+			.into_iter()
+			.flatten()
+	}));
+}
+
+/// Records arbitrary terms for search suggestions
+pub fn record_terms<'a>(terms: impl Iterator<Item = &'a str>) {
+	let mut vocabulary = lock(&VOCABULARY);
+
+	for term in terms {
+		let term = term.trim().to_lowercase();
+		if term.is_empty() || vocabulary.terms.contains(&term) {
+			continue;
+		}
+
+		vocabulary.terms.insert(term.clone());
+		vocabulary.queue.push_back(term);
+
+		if vocabulary.queue.len() > VOCABULARY_MAX_SIZE {
+			if let Some(oldest) = vocabulary.queue.pop_front() {
+				vocabulary.terms.remove(&oldest);
+			}
+		}
+	}
+}
+
+/// Suggests known terms that are close to the query
+/// Terms within a small edit distance rank first, followed by bigram similarity
+pub fn suggest(query: &str, limit: usize) -> Vec<String> {
+	let query = query.trim().to_lowercase();
+	if query.is_empty() {
+		return Vec::new();
+	}
+
+	let length = query.chars().count();
+	let max_distance = (length / 3).max(1);
+
+	// Only cheap length checks run under the lock, so recording terms isn't stalled by scoring
+	let candidates = {
+		let vocabulary = lock(&VOCABULARY);
+
+		vocabulary
+			.terms
+			.iter()
+			.filter(|term| {
+				**term != query && is_candidate(length, term.chars().count(), max_distance)
+			})
+			.cloned()
+			.collect::<Vec<String>>()
+	};
+
+	let mut suggestions = candidates
+		.into_iter()
+		.filter_map(|term| {
+			let distance = edit_distance(&query, &term);
+			let similarity = bigram_similarity(&query, &term);
+
+			match distance <= max_distance || similarity >= SUGGESTION_MIN_SIMILARITY {
+				true => Some((distance, similarity, term)),
+				false => None,
+			}
+		})
+		.collect::<Vec<(usize, f64, String)>>();
+
+	suggestions.sort_by(|left, right| {
+		left.0
+			.cmp(&right.0)
+			.then_with(|| right.1.total_cmp(&left.1))
+			.then_with(|| left.2.cmp(&right.2))
+	});
+
+	suggestions
+		.into_iter()
+		.take(limit)
+		.map(|(_, _, term)| term)
+		.collect()
+}
+
+/// Checks if a term's length allows it to be suggested for a query
+/// The edit distance is at least the length difference, and a bigram similarity of one half
+/// needs the shorter string to have at least a third of the longer string's bigrams
+fn is_candidate(query_length: usize, term_length: usize, max_distance: usize) -> bool {
+	if query_length.abs_diff(term_length) <= max_distance {
+		return true;
+	}
+
+	let query_bigrams = query_length.saturating_sub(1);
+	let term_bigrams = term_length.saturating_sub(1);
+	term_bigrams <= query_bigrams * 3 && query_bigrams <= term_bigrams * 3
+}